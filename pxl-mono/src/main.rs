@@ -252,6 +252,7 @@ void main() {
       if let Event::Button {
         button: Button::Action,
         state: ButtonState::Pressed,
+        ..
       } = event
       {
         self.pattern = self.pattern.cycle();
@@ -5,15 +5,12 @@ extern crate rand;
 
 use pxl::*;
 use rand::prelude::*;
-use std::{
-  f64, sync::{Arc, Mutex},
-};
+use std::sync::{Arc, Mutex};
 
 use Cell::*;
 
 const WIDTH: usize = 512;
 const HEIGHT: usize = 512;
-const TAU: f64 = f64::consts::PI * 2.0;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Cell {
@@ -39,17 +36,15 @@ impl Cell {
 }
 
 struct LifeSynthesizer {
+  oscillator: Oscillator,
   intensity: f32,
 }
 
 impl Synthesizer for LifeSynthesizer {
-  fn synthesize(&mut self, mut samples_played: u64, samples: &mut [AudioSample]) {
+  fn synthesize(&mut self, _samples_played: u64, samples: &mut [AudioSample]) {
+    let mut gain = Gain::new(self.intensity);
     for sample in samples {
-      let time = samples_played as f64 / SAMPLES_PER_SECOND as f64;
-      let s = (time * 440.0 * TAU).sin() as f32 * self.intensity;
-      sample.left = s;
-      sample.right = s;
-      samples_played += 1;
+      *sample = gain.process(self.oscillator.generate());
     }
   }
 }
@@ -118,7 +113,10 @@ impl Program for Life {
         .into_iter()
         .map(|_| if random() { Alive } else { Dead })
         .collect(),
-      synthesizer: Arc::new(Mutex::new(LifeSynthesizer { intensity: 0.0 })),
+      synthesizer: Arc::new(Mutex::new(LifeSynthesizer {
+        oscillator: Oscillator::new(Waveform::Sine, 440.0),
+        intensity: 0.0,
+      })),
     }
   }
 
@@ -130,15 +128,9 @@ impl Program for Life {
     "life"
   }
 
-  fn tick(&mut self, _elapsed: Duration, events: &[Event]) {
-    for event in events {
-      if let Event::Button {
-        state: ButtonState::Released,
-        button: Button::Action,
-      } = event
-      {
-        self.reset();
-      }
+  fn update(&mut self, _elapsed: Duration, input: &Input) {
+    if input.was_released(Button::Action) {
+      self.reset();
     }
 
     self.step();
@@ -246,5 +238,9 @@ void main() {
 }
 
 fn main() {
-  pxl::run::<Life>();
+  if std::env::args().any(|arg| arg == "--terminal") {
+    pxl::run_terminal::<Life>();
+  } else {
+    pxl::run::<Life>();
+  }
 }
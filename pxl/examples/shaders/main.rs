@@ -59,24 +59,28 @@ impl Program for Shaders {
         Event::Button {
           state: ButtonState::Pressed,
           button: Button::Right,
+          ..
         } => {
           self.active_fragment_shader_index += 1;
         }
         Event::Button {
           state: ButtonState::Pressed,
           button: Button::Left,
+          ..
         } => {
           self.active_fragment_shader_index += FRAGMENT_SHADERS.len() - 1;
         }
         Event::Button {
           state: ButtonState::Pressed,
           button: Button::Up,
+          ..
         } => {
           self.active_vertex_shader_index += 1;
         }
         Event::Button {
           state: ButtonState::Pressed,
           button: Button::Down,
+          ..
         } => {
           self.active_vertex_shader_index += VERTEX_SHADERS.len() - 1;
         }
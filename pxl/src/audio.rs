@@ -0,0 +1,243 @@
+//! Rendering and saving audio without an audio device
+
+use std::{
+  fs::File,
+  io::{self, BufWriter, Write},
+};
+
+use {AudioSample, Duration, Error, Path, Synthesizer, SAMPLES_PER_SECOND};
+
+/// The number of samples requested from the synthesizer at a time
+/// by `render_audio`
+pub const DEFAULT_BUFFER_SIZE: usize = 512;
+
+/// The sample format of a WAV file written by `write_wav`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum WavFormat {
+  /// 16-bit signed integer samples, supported by nearly everything.
+  /// Samples are clamped to between -1.0 and 1.0.
+  Int16,
+  /// 32-bit floating point samples, which are written unaltered
+  Float32,
+}
+
+/// The number of samples played in `duration`, rounded
+pub fn sample_count(duration: Duration) -> usize {
+  let seconds = duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9;
+  (seconds * f64::from(SAMPLES_PER_SECOND)).round() as usize
+}
+
+/// Synthesize `duration` of audio from `synthesizer`, without an
+/// audio device, returning the samples
+///
+/// `Synthesizer::synthesize` is called in the same way that it is
+/// when audio is played, with buffers of `DEFAULT_BUFFER_SIZE`
+/// samples and `samples_played` starting at zero. Useful for testing
+/// synthesizers, and, with `write_wav`, for saving their output:
+///
+/// ```no_run
+/// use pxl::*;
+///
+/// struct Silence;
+///
+/// impl Synthesizer for Silence {}
+///
+/// let samples = render_audio(&mut Silence, Duration::from_secs(10));
+/// write_wav("silence.wav", &samples, WavFormat::Int16).unwrap();
+/// ```
+pub fn render_audio(synthesizer: &mut Synthesizer, duration: Duration) -> Vec<AudioSample> {
+  render_audio_with_buffer_size(synthesizer, duration, DEFAULT_BUFFER_SIZE)
+}
+
+/// Like `render_audio`, but calling `Synthesizer::synthesize` with
+/// buffers of `buffer_size` samples. The last buffer may be shorter.
+///
+/// Panics if `buffer_size` is zero.
+pub fn render_audio_with_buffer_size(
+  synthesizer: &mut Synthesizer,
+  duration: Duration,
+  buffer_size: usize,
+) -> Vec<AudioSample> {
+  assert!(buffer_size > 0, "buffer size must be greater than zero");
+
+  let mut samples = vec![AudioSample::default(); sample_count(duration)];
+
+  let mut samples_played = 0;
+  for buffer in samples.chunks_mut(buffer_size) {
+    synthesizer.synthesize(samples_played, buffer);
+    samples_played += buffer.len() as u64;
+  }
+
+  samples
+}
+
+fn write_u16(writer: &mut impl Write, value: u16) -> io::Result<()> {
+  writer.write_all(&[value as u8, (value >> 8) as u8])
+}
+
+fn write_u32(writer: &mut impl Write, value: u32) -> io::Result<()> {
+  write_u16(writer, value as u16)?;
+  write_u16(writer, (value >> 16) as u16)
+}
+
+/// Write `samples` to `writer` as a stereo WAV file in `format`,
+/// with a sample rate of `SAMPLES_PER_SECOND`
+fn encode(writer: &mut impl Write, samples: &[AudioSample], format: WavFormat) -> io::Result<()> {
+  const CHANNELS: u16 = 2;
+
+  let (format_tag, bytes_per_sample) = match format {
+    WavFormat::Int16 => (1, 2),
+    WavFormat::Float32 => (3, 4),
+  };
+
+  // Formats other than integer PCM have an extended format chunk
+  // and a fact chunk
+  let pcm = format == WavFormat::Int16;
+  let format_length = if pcm { 16 } else { 18 };
+  let fact_length = if pcm { 0 } else { 12 };
+
+  let block_align = CHANNELS * bytes_per_sample;
+  let data_length = samples.len() as u32 * u32::from(block_align);
+
+  writer.write_all(b"RIFF")?;
+  write_u32(
+    writer,
+    4 + 8 + format_length + fact_length + 8 + data_length,
+  )?;
+  writer.write_all(b"WAVE")?;
+
+  writer.write_all(b"fmt ")?;
+  write_u32(writer, format_length)?;
+  write_u16(writer, format_tag)?;
+  write_u16(writer, CHANNELS)?;
+  write_u32(writer, SAMPLES_PER_SECOND)?;
+  write_u32(writer, SAMPLES_PER_SECOND * u32::from(block_align))?;
+  write_u16(writer, block_align)?;
+  write_u16(writer, bytes_per_sample * 8)?;
+
+  if !pcm {
+    // Size of the extension
+    write_u16(writer, 0)?;
+
+    writer.write_all(b"fact")?;
+    write_u32(writer, 4)?;
+    write_u32(writer, samples.len() as u32)?;
+  }
+
+  writer.write_all(b"data")?;
+  write_u32(writer, data_length)?;
+
+  for sample in samples {
+    for &channel in &[sample.left, sample.right] {
+      match format {
+        WavFormat::Int16 => {
+          let value = (channel.max(-1.0).min(1.0) * 32_767.0) as i16;
+          write_u16(writer, value as u16)?;
+        }
+        WavFormat::Float32 => write_u32(writer, channel.to_bits())?,
+      }
+    }
+  }
+
+  Ok(())
+}
+
+/// Write `samples` to a WAV file at `path`
+pub fn write(path: &Path, samples: &[AudioSample], format: WavFormat) -> io::Result<()> {
+  let mut writer = BufWriter::new(File::create(path)?);
+  encode(&mut writer, samples, format)?;
+  writer.flush()
+}
+
+/// Write `samples` to a stereo WAV file at `path`, with a sample
+/// rate of `SAMPLES_PER_SECOND` and sample format `format`
+pub fn write_wav(
+  path: impl AsRef<Path>,
+  samples: &[AudioSample],
+  format: WavFormat,
+) -> Result<(), Error> {
+  let path = path.as_ref();
+  write(path, samples, format).map_err(|io_error| Error::WavIo {
+    io_error,
+    path: path.to_path_buf(),
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  /// Records the arguments of each call to `synthesize`
+  struct Calls {
+    calls: Vec<(u64, usize)>,
+  }
+
+  impl Synthesizer for Calls {
+    fn synthesize(&mut self, samples_played: u64, output_buffer: &mut [AudioSample]) {
+      self.calls.push((samples_played, output_buffer.len()));
+      for (i, sample) in output_buffer.iter_mut().enumerate() {
+        sample.left = (samples_played + i as u64) as f32;
+      }
+    }
+  }
+
+  #[test]
+  fn buffers() {
+    let mut synthesizer = Calls { calls: Vec::new() };
+    let samples = render_audio_with_buffer_size(&mut synthesizer, Duration::from_millis(25), 500);
+
+    assert_eq!(samples.len(), 1200);
+    assert_eq!(synthesizer.calls, [(0, 500), (500, 500), (1000, 200)]);
+    assert!(samples
+      .iter()
+      .enumerate()
+      .all(|(i, sample)| sample.left == i as f32));
+  }
+
+  #[test]
+  fn int16() {
+    let samples = [
+      AudioSample {
+        left: 1.0,
+        right: -2.0,
+      },
+      AudioSample {
+        left: 0.0,
+        right: 0.5,
+      },
+    ];
+
+    let mut bytes = Vec::new();
+    encode(&mut bytes, &samples, WavFormat::Int16).unwrap();
+
+    assert_eq!(bytes.len(), 44 + 8);
+    assert_eq!(&bytes[0..4], b"RIFF");
+    assert_eq!(&bytes[4..8], &[44, 0, 0, 0]);
+    assert_eq!(&bytes[20..22], &[1, 0]);
+    assert_eq!(&bytes[24..28], &[0x80, 0xBB, 0, 0]);
+    assert_eq!(&bytes[40..44], &[8, 0, 0, 0]);
+    assert_eq!(
+      &bytes[44..],
+      &[0xFF, 0x7F, 0x01, 0x80, 0x00, 0x00, 0xFF, 0x3F]
+    );
+  }
+
+  #[test]
+  fn float32() {
+    let samples = [AudioSample {
+      left: 1.0,
+      right: -2.0,
+    }];
+
+    let mut bytes = Vec::new();
+    encode(&mut bytes, &samples, WavFormat::Float32).unwrap();
+
+    assert_eq!(bytes.len(), 58 + 8);
+    assert_eq!(&bytes[4..8], &[58, 0, 0, 0]);
+    assert_eq!(&bytes[20..22], &[3, 0]);
+    assert_eq!(&bytes[38..42], b"fact");
+    assert_eq!(&bytes[46..50], &[1, 0, 0, 0]);
+    assert_eq!(&bytes[50..54], b"data");
+    assert_eq!(&bytes[58..], &[0, 0, 0x80, 0x3F, 0, 0, 0, 0xC0]);
+  }
+}
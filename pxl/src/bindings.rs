@@ -0,0 +1,164 @@
+//! Mapping keys to buttons
+
+use std::{collections::HashMap, fs, path::Path, str::FromStr};
+
+use {Button, Error, KeyCode};
+
+/// A button belonging to a particular player
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Binding {
+  /// The index of the player the button belongs to,
+  /// starting from zero
+  pub player: usize,
+  /// The button
+  pub button: Button,
+}
+
+/// A mapping from keys to buttons, returned by `Program::bindings`
+///
+/// When a bound key is pressed or released, the runtime generates
+/// an `Event::Button` for the key's button and player, in addition
+/// to the usual `Event::KeyDown` or `Event::KeyUp`.
+///
+/// The default bindings map the arrow keys to `Left`, `Right`, `Up`
+/// and `Down`, and the spacebar to `Action`, all for player zero.
+///
+/// Bindings may be loaded from a file, so that players can change
+/// them without recompiling the program. Each line of a bindings
+/// file binds a key to a button, optionally followed by the index
+/// of the player the button belongs to, which defaults to zero.
+/// Blank lines and lines starting with `#` are ignored:
+///
+/// ```text
+/// # player one uses WASD
+/// w = up
+/// a = left
+/// s = down
+/// d = right
+/// space = action
+///
+/// # player two uses the arrow keys
+/// up = up 1
+/// left = left 1
+/// down = down 1
+/// right = right 1
+/// enter = action 1
+/// ```
+///
+/// Keys and buttons are named as in `KeyCode::name` and `Button::name`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bindings {
+  keys: HashMap<KeyCode, Binding>,
+}
+
+impl Bindings {
+  /// Create a new set of bindings with no keys bound
+  pub fn new() -> Bindings {
+    Bindings {
+      keys: HashMap::new(),
+    }
+  }
+
+  /// Bind `key` to `player`'s `button`, replacing any
+  /// existing binding for `key`
+  pub fn bind(&mut self, key: KeyCode, player: usize, button: Button) {
+    self.keys.insert(key, Binding { player, button });
+  }
+
+  /// Remove the binding for `key`, if any
+  pub fn unbind(&mut self, key: KeyCode) {
+    self.keys.remove(&key);
+  }
+
+  /// Get the binding for `key`, if any
+  pub fn get(&self, key: KeyCode) -> Option<Binding> {
+    self.keys.get(&key).cloned()
+  }
+
+  /// Load bindings from the file at `path`
+  pub fn load(path: impl AsRef<Path>) -> Result<Bindings, Error> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path).map_err(|io_error| Error::BindingsIo {
+      io_error,
+      path: path.to_path_buf(),
+    })?;
+    text.parse().map_err(|line| Error::BindingsParse {
+      path: path.to_path_buf(),
+      line,
+    })
+  }
+}
+
+impl Default for Bindings {
+  fn default() -> Bindings {
+    let mut bindings = Bindings::new();
+    bindings.bind(KeyCode::Left, 0, Button::Left);
+    bindings.bind(KeyCode::Right, 0, Button::Right);
+    bindings.bind(KeyCode::Up, 0, Button::Up);
+    bindings.bind(KeyCode::Down, 0, Button::Down);
+    bindings.bind(KeyCode::Space, 0, Button::Action);
+    bindings
+  }
+}
+
+impl FromStr for Bindings {
+  /// The number of the first invalid line
+  type Err = usize;
+
+  fn from_str(text: &str) -> Result<Bindings, usize> {
+    let mut bindings = Bindings::new();
+
+    for (i, line) in text.lines().enumerate() {
+      let line = line.trim();
+
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+
+      let number = i + 1;
+      let words = line.split_whitespace().collect::<Vec<&str>>();
+
+      let (key, button, player) = match words.as_slice() {
+        [key, "=", button] => (key, button, 0),
+        [key, "=", button, player] => (key, button, player.parse().map_err(|_| number)?),
+        _ => return Err(number),
+      };
+
+      let key = KeyCode::from_name(key).ok_or(number)?;
+      let button = Button::from_name(button).ok_or(number)?;
+
+      bindings.bind(key, player, button);
+    }
+
+    Ok(bindings)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn parse() {
+    let bindings = "
+      # comment
+      w = up
+      Enter = action2 1
+    ".parse::<Bindings>()
+      .unwrap();
+
+    let mut expected = Bindings::new();
+    expected.bind(KeyCode::W, 0, Button::Up);
+    expected.bind(KeyCode::Enter, 1, Button::Action2);
+
+    assert_eq!(bindings, expected);
+  }
+
+  #[test]
+  fn parse_errors() {
+    assert_eq!("w up".parse::<Bindings>(), Err(1));
+    assert_eq!("w = up\nhyper = up".parse::<Bindings>(), Err(2));
+    assert_eq!("w = jump".parse::<Bindings>(), Err(1));
+    assert_eq!("w = up one".parse::<Bindings>(), Err(1));
+  }
+}
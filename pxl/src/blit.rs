@@ -0,0 +1,304 @@
+//! Copying images to pixel buffers
+
+use {Canvas, Image, Pixel};
+
+/// How the pixels of an image are combined with the pixels they
+/// are drawn over
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Blend {
+  /// Replace the destination pixels with the image's pixels,
+  /// including their alpha
+  Replace,
+  /// Draw the image over the destination, so that where the image
+  /// is partially transparent the destination shows through
+  AlphaOver,
+  /// Add the image's color, scaled by its alpha, to the destination
+  Additive,
+  /// Multiply the destination's color by the image's color, with
+  /// fully transparent pixels leaving the destination unchanged
+  Multiply,
+}
+
+/// A rotation applied to an image when blitting
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Rotation {
+  /// No rotation
+  None,
+  /// Rotate 90° clockwise
+  Clockwise90,
+  /// Rotate 180°
+  Rotate180,
+  /// Rotate 90° counterclockwise
+  Counterclockwise90,
+}
+
+/// Options for `Canvas::blit_with`
+///
+/// ```
+/// use pxl::*;
+///
+/// let blit = Blit {
+///   flip_horizontal: true,
+///   ..Blit::default()
+/// };
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Blit {
+  /// The region of the image to draw, as `(x, y, width, height)`,
+  /// or `None` to draw the whole image. Useful for sprite sheets.
+  pub region: Option<(usize, usize, usize, usize)>,
+  /// Mirror the image left to right
+  pub flip_horizontal: bool,
+  /// Mirror the image top to bottom
+  pub flip_vertical: bool,
+  /// Rotate the image, after flipping it
+  pub rotation: Rotation,
+  /// How to combine the image with the pixels it is drawn over
+  pub blend: Blend,
+}
+
+impl Default for Blit {
+  fn default() -> Blit {
+    Blit {
+      region: None,
+      flip_horizontal: false,
+      flip_vertical: false,
+      rotation: Rotation::None,
+      blend: Blend::AlphaOver,
+    }
+  }
+}
+
+/// Combine `source` with `destination` according to `blend`
+fn blend(blend: Blend, source: Pixel, destination: Pixel) -> Pixel {
+  let alpha = source.alpha;
+
+  match blend {
+    Blend::Replace => source,
+    Blend::AlphaOver => source.over(destination),
+    Blend::Additive => Pixel {
+      red: destination.red + source.red * alpha,
+      green: destination.green + source.green * alpha,
+      blue: destination.blue + source.blue * alpha,
+      alpha: destination.alpha,
+    },
+    Blend::Multiply => {
+      let factor = |source: f32| source * alpha + (1.0 - alpha);
+      Pixel {
+        red: destination.red * factor(source.red),
+        green: destination.green * factor(source.green),
+        blue: destination.blue * factor(source.blue),
+        alpha: destination.alpha,
+      }
+    }
+  }
+}
+
+impl<'pixels> Canvas<'pixels> {
+  /// Draw `image` with its upper left corner at `(x, y)`, drawing
+  /// it over the existing pixels according to their alpha
+  pub fn blit(&mut self, image: &Image, x: isize, y: isize) {
+    self.blit_with(image, x, y, &Blit::default());
+  }
+
+  /// Draw `image`, transformed and blended according to `blit`,
+  /// with the upper left corner of the transformed image at `(x, y)`
+  pub fn blit_with(&mut self, image: &Image, x: isize, y: isize, blit: &Blit) {
+    let (left, top, width, height) = blit
+      .region
+      .unwrap_or((0, 0, image.width, image.height));
+
+    // Clip the region to the image
+    let left = left.min(image.width);
+    let top = top.min(image.height);
+    let width = width.min(image.width - left);
+    let height = height.min(image.height - top);
+
+    let (output_width, output_height) = match blit.rotation {
+      Rotation::None | Rotation::Rotate180 => (width, height),
+      Rotation::Clockwise90 | Rotation::Counterclockwise90 => (height, width),
+    };
+
+    // Clip the transformed image to the canvas
+    let start_u = (-x).max(0) as usize;
+    let start_v = (-y).max(0) as usize;
+    let end_u = (self.width() as isize - x).max(0).min(output_width as isize) as usize;
+    let end_v = (self.height() as isize - y).max(0).min(output_height as isize) as usize;
+
+    for v in start_v..end_v {
+      for u in start_u..end_u {
+        // Undo the rotation, and then the flips, to find the
+        // source pixel for this output pixel
+        let (fx, fy) = match blit.rotation {
+          Rotation::None => (u, v),
+          Rotation::Clockwise90 => (v, height - 1 - u),
+          Rotation::Rotate180 => (width - 1 - u, height - 1 - v),
+          Rotation::Counterclockwise90 => (width - 1 - v, u),
+        };
+
+        let sx = if blit.flip_horizontal { width - 1 - fx } else { fx };
+        let sy = if blit.flip_vertical { height - 1 - fy } else { fy };
+
+        let source = image.pixels[left + sx + (top + sy) * image.width];
+
+        let dx = x + u as isize;
+        let dy = y + v as isize;
+        if let Some(destination) = self.get(dx, dy) {
+          self.set(dx, dy, blend(blit.blend, source, destination));
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use {rgb, rgba};
+
+  fn gray(value: f32) -> Pixel {
+    rgb(value, value, value)
+  }
+
+  /// A 3x2 image with distinct grays, numbered left to right,
+  /// top to bottom
+  static PIXELS: [Pixel; 6] = [
+    Pixel {
+      red: 0.1,
+      green: 0.1,
+      blue: 0.1,
+      alpha: 1.0,
+    },
+    Pixel {
+      red: 0.2,
+      green: 0.2,
+      blue: 0.2,
+      alpha: 1.0,
+    },
+    Pixel {
+      red: 0.3,
+      green: 0.3,
+      blue: 0.3,
+      alpha: 1.0,
+    },
+    Pixel {
+      red: 0.4,
+      green: 0.4,
+      blue: 0.4,
+      alpha: 1.0,
+    },
+    Pixel {
+      red: 0.5,
+      green: 0.5,
+      blue: 0.5,
+      alpha: 1.0,
+    },
+    Pixel {
+      red: 0.6,
+      green: 0.6,
+      blue: 0.6,
+      alpha: 1.0,
+    },
+  ];
+
+  static IMAGE: Image = Image {
+    width: 3,
+    height: 2,
+    pixels: &PIXELS,
+  };
+
+  /// Blit `IMAGE` at `(x, y)` to a black canvas with dimensions
+  /// `resolution`, returning each pixel's gray value multiplied
+  /// by ten
+  fn blit(resolution: (usize, usize), x: isize, y: isize, blit: Blit) -> Vec<u32> {
+    let mut pixels = vec![gray(0.0); resolution.0 * resolution.1];
+    Canvas::new(&mut pixels, resolution).blit_with(&IMAGE, x, y, &blit);
+    pixels
+      .iter()
+      .map(|pixel| (pixel.red * 10.0).round() as u32)
+      .collect()
+  }
+
+  #[test]
+  fn whole_image() {
+    assert_eq!(
+      blit((4, 3), 1, 1, Blit::default()),
+      [0, 0, 0, 0, 0, 1, 2, 3, 0, 4, 5, 6]
+    );
+  }
+
+  #[test]
+  fn clipping() {
+    assert_eq!(blit((2, 2), -1, 1, Blit::default()), [0, 0, 2, 3]);
+    assert_eq!(blit((2, 2), 5, 5, Blit::default()), [0, 0, 0, 0]);
+  }
+
+  #[test]
+  fn region() {
+    let region = Blit {
+      region: Some((1, 0, 2, 5)),
+      ..Blit::default()
+    };
+    assert_eq!(blit((2, 2), 0, 0, region), [2, 3, 5, 6]);
+  }
+
+  #[test]
+  fn flips() {
+    let horizontal = Blit {
+      flip_horizontal: true,
+      ..Blit::default()
+    };
+    assert_eq!(blit((3, 2), 0, 0, horizontal), [3, 2, 1, 6, 5, 4]);
+
+    let vertical = Blit {
+      flip_vertical: true,
+      ..Blit::default()
+    };
+    assert_eq!(blit((3, 2), 0, 0, vertical), [4, 5, 6, 1, 2, 3]);
+  }
+
+  #[test]
+  fn rotations() {
+    let rotate = |rotation| Blit {
+      rotation,
+      ..Blit::default()
+    };
+    assert_eq!(
+      blit((2, 3), 0, 0, rotate(Rotation::Clockwise90)),
+      [4, 1, 5, 2, 6, 3]
+    );
+    assert_eq!(
+      blit((3, 2), 0, 0, rotate(Rotation::Rotate180)),
+      [6, 5, 4, 3, 2, 1]
+    );
+    assert_eq!(
+      blit((2, 3), 0, 0, rotate(Rotation::Counterclockwise90)),
+      [3, 6, 2, 5, 1, 4]
+    );
+  }
+
+  #[test]
+  fn blend_modes() {
+    let source = rgba(1.0, 0.5, 0.0, 0.5);
+    let destination = rgb(0.0, 0.5, 1.0);
+
+    assert_eq!(blend(Blend::Replace, source, destination), source);
+    assert_eq!(
+      blend(Blend::AlphaOver, source, destination),
+      rgb(0.5, 0.5, 0.5)
+    );
+    assert_eq!(
+      blend(Blend::Additive, source, destination),
+      rgb(0.5, 0.75, 1.0)
+    );
+    assert_eq!(
+      blend(Blend::Multiply, source, destination),
+      rgb(0.0, 0.375, 0.5)
+    );
+    assert_eq!(
+      blend(Blend::AlphaOver, rgba(1.0, 1.0, 1.0, 0.0), destination),
+      destination
+    );
+  }
+}
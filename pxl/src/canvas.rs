@@ -0,0 +1,512 @@
+//! Drawing primitives for pixel buffers
+
+use Pixel;
+
+/// A pixel buffer with known dimensions, such as the slice passed to
+/// `Program::render`, with methods for drawing to it.
+///
+/// Coordinates are signed, with `(0, 0)` in the upper left corner.
+/// Anything drawn outside of the buffer is clipped, so shapes may
+/// be partially or entirely off screen.
+///
+/// ```
+/// use pxl::*;
+///
+/// let mut pixels = vec![rgb(0.0, 0.0, 0.0); 16 * 16];
+/// let mut canvas = Canvas::new(&mut pixels, (16, 16));
+/// canvas.line(0, 0, 15, 15, rgb(1.0, 1.0, 1.0));
+/// assert_eq!(canvas.get(7, 7), Some(rgb(1.0, 1.0, 1.0)));
+/// ```
+pub struct Canvas<'pixels> {
+  pixels: &'pixels mut [Pixel],
+  width: usize,
+  height: usize,
+}
+
+/// The last coordinate of a span of `length` pixels, which must be at
+/// least one, starting at `start`, saturating instead of overflowing
+fn last(start: isize, length: usize) -> isize {
+  let length = length.min(isize::max_value() as usize) as isize;
+  start.saturating_add(length - 1)
+}
+
+impl<'pixels> Canvas<'pixels> {
+  /// Create a canvas over `pixels`, a buffer of pixels with
+  /// dimensions `resolution`, in the same order as the slice
+  /// passed to `Program::render`
+  ///
+  /// Panics if `pixels` does not contain `resolution.0 * resolution.1`
+  /// pixels.
+  pub fn new(pixels: &'pixels mut [Pixel], resolution: (usize, usize)) -> Canvas<'pixels> {
+    assert_eq!(
+      pixels.len(),
+      resolution.0 * resolution.1,
+      "pixel buffer does not match resolution"
+    );
+
+    Canvas {
+      width: resolution.0,
+      height: resolution.1,
+      pixels,
+    }
+  }
+
+  /// The width of the canvas in pixels
+  pub fn width(&self) -> usize {
+    self.width
+  }
+
+  /// The height of the canvas in pixels
+  pub fn height(&self) -> usize {
+    self.height
+  }
+
+  /// The canvas's pixels
+  pub fn pixels(&mut self) -> &mut [Pixel] {
+    self.pixels
+  }
+
+  /// The index of the pixel at `(x, y)`, or `None` if it is
+  /// outside of the canvas
+  fn index(&self, x: isize, y: isize) -> Option<usize> {
+    if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+      None
+    } else {
+      Some(x as usize + y as usize * self.width)
+    }
+  }
+
+  /// Get the color of the pixel at `(x, y)`, or `None` if it is
+  /// outside of the canvas
+  pub fn get(&self, x: isize, y: isize) -> Option<Pixel> {
+    self.index(x, y).map(|i| self.pixels[i])
+  }
+
+  /// Set the pixel at `(x, y)` to `color`
+  pub fn set(&mut self, x: isize, y: isize, color: Pixel) {
+    if let Some(i) = self.index(x, y) {
+      self.pixels[i] = color;
+    }
+  }
+
+  /// Set every pixel to `color`
+  pub fn fill(&mut self, color: Pixel) {
+    for pixel in self.pixels.iter_mut() {
+      *pixel = color;
+    }
+  }
+
+  /// Draw a horizontal line from `(x0, y)` to `(x1, y)` inclusive
+  fn span(&mut self, x0: isize, x1: isize, y: isize, color: Pixel) {
+    if y < 0 || y as usize >= self.height || self.width == 0 {
+      return;
+    }
+
+    let x0 = x0.max(0);
+    let x1 = x1.min(self.width as isize - 1);
+
+    if x0 > x1 {
+      return;
+    }
+
+    let row = y as usize * self.width;
+    for pixel in &mut self.pixels[row + x0 as usize..=row + x1 as usize] {
+      *pixel = color;
+    }
+  }
+
+  /// Draw a line from `(x0, y0)` to `(x1, y1)` inclusive, using
+  /// Bresenham's algorithm
+  ///
+  /// The line is first clipped to the canvas, so lines that are far
+  /// off the canvas take no longer to draw than lines on it.
+  pub fn line(&mut self, x0: isize, y0: isize, x1: isize, y1: isize, color: Pixel) {
+    let ((x0, y0), (x1, y1)) = match self.clip(x0, y0, x1, y1) {
+      Some(clipped) => clipped,
+      None => return,
+    };
+
+    let dx = (x1 - x0).abs();
+    let dy = -(y1 - y0).abs();
+    let sx = if x0 < x1 { 1 } else { -1 };
+    let sy = if y0 < y1 { 1 } else { -1 };
+    let mut error = dx + dy;
+    let (mut x, mut y) = (x0, y0);
+
+    loop {
+      self.set(x, y, color);
+
+      if x == x1 && y == y1 {
+        break;
+      }
+
+      let doubled = error * 2;
+
+      if doubled >= dy {
+        error += dy;
+        x += sx;
+      }
+
+      if doubled <= dx {
+        error += dx;
+        y += sy;
+      }
+    }
+  }
+
+  /// Clip the line from `(x0, y0)` to `(x1, y1)` to the canvas and a
+  /// one pixel margin around it, using the Liang-Barsky algorithm,
+  /// returning the clipped endpoints, or `None` if the line doesn't
+  /// cross the canvas. Endpoints that are already inside are returned
+  /// unchanged.
+  fn clip(
+    &self,
+    x0: isize,
+    y0: isize,
+    x1: isize,
+    y1: isize,
+  ) -> Option<((isize, isize), (isize, isize))> {
+    let (fx0, fy0) = (x0 as f64, y0 as f64);
+    let (dx, dy) = (x1 as f64 - fx0, y1 as f64 - fy0);
+
+    // Points where the line crosses each edge of the margin. The
+    // coordinate of the edge is used as is, and only the other is
+    // interpolated, so that the clipped points are accurate even for
+    // lines with very distant endpoints.
+    let left = -1.0;
+    let right = self.width as f64;
+    let top = -1.0;
+    let bottom = self.height as f64;
+    let at_x = |x: f64| (x, fy0 + (x - fx0) * dy / dx);
+    let at_y = |y: f64| (fx0 + (y - fy0) * dx / dy, y);
+
+    // The parameters of the clipped endpoints along the line, from 0.0
+    // at `(x0, y0)` to 1.0 at `(x1, y1)`, and their positions
+    let mut start = (0.0, None);
+    let mut end = (1.0, None);
+
+    for &(p, q, edge, x) in &[
+      (-dx, fx0 - left, left, true),
+      (dx, right - fx0, right, true),
+      (-dy, fy0 - top, top, false),
+      (dy, bottom - fy0, bottom, false),
+    ] {
+      if p == 0.0 {
+        if q < 0.0 {
+          return None;
+        }
+        continue;
+      }
+
+      let t = q / p;
+      let point = Some(if x { at_x(edge) } else { at_y(edge) });
+
+      if p < 0.0 && t > start.0 {
+        start = (t, point);
+      } else if p > 0.0 && t < end.0 {
+        end = (t, point);
+      }
+    }
+
+    if start.0 > end.0 {
+      return None;
+    }
+
+    let round = |(x, y): (f64, f64)| (x.round() as isize, y.round() as isize);
+
+    Some((
+      start.1.map(round).unwrap_or((x0, y0)),
+      end.1.map(round).unwrap_or((x1, y1)),
+    ))
+  }
+
+  /// Draw the outline of the rectangle with upper left corner at
+  /// `(x, y)` and dimensions `width` by `height`
+  pub fn rect(&mut self, x: isize, y: isize, width: usize, height: usize, color: Pixel) {
+    if width == 0 || height == 0 {
+      return;
+    }
+
+    let right = last(x, width);
+    let bottom = last(y, height);
+
+    self.span(x, right, y, color);
+    self.span(x, right, bottom, color);
+    self.line(x, y, x, bottom, color);
+    self.line(right, y, right, bottom, color);
+  }
+
+  /// Fill the rectangle with upper left corner at `(x, y)` and
+  /// dimensions `width` by `height`
+  pub fn fill_rect(&mut self, x: isize, y: isize, width: usize, height: usize, color: Pixel) {
+    if width == 0 || height == 0 {
+      return;
+    }
+
+    // Only rows on the canvas are drawn, and `span` clips each row
+    let top = y.max(0);
+    let bottom = last(y, height).min(self.height as isize - 1);
+
+    for row in top..=bottom {
+      self.span(x, last(x, width), row, color);
+    }
+  }
+
+  /// Draw the outline of the circle centered at `(x, y)` with
+  /// radius `radius`, using the midpoint circle algorithm
+  pub fn circle(&mut self, x: isize, y: isize, radius: usize, color: Pixel) {
+    let mut dx = radius as isize;
+    let mut dy = 0;
+    let mut error = 1 - dx;
+
+    while dx >= dy {
+      for &(px, py) in &[
+        (dx, dy),
+        (dy, dx),
+        (-dy, dx),
+        (-dx, dy),
+        (-dx, -dy),
+        (-dy, -dx),
+        (dy, -dx),
+        (dx, -dy),
+      ] {
+        self.set(x + px, y + py, color);
+      }
+
+      dy += 1;
+      if error < 0 {
+        error += 2 * dy + 1;
+      } else {
+        dx -= 1;
+        error += 2 * (dy - dx) + 1;
+      }
+    }
+  }
+
+  /// Fill the circle centered at `(x, y)` with radius `radius`
+  pub fn fill_circle(&mut self, x: isize, y: isize, radius: usize, color: Pixel) {
+    let mut dx = radius as isize;
+    let mut dy = 0;
+    let mut error = 1 - dx;
+
+    while dx >= dy {
+      self.span(x - dx, x + dx, y + dy, color);
+      self.span(x - dx, x + dx, y - dy, color);
+      self.span(x - dy, x + dy, y + dx, color);
+      self.span(x - dy, x + dy, y - dx, color);
+
+      dy += 1;
+      if error < 0 {
+        error += 2 * dy + 1;
+      } else {
+        dx -= 1;
+        error += 2 * (dy - dx) + 1;
+      }
+    }
+  }
+
+  /// Draw the outline of the closed polygon with vertices `points`
+  pub fn polygon(&mut self, points: &[(isize, isize)], color: Pixel) {
+    for (i, &(x0, y0)) in points.iter().enumerate() {
+      let (x1, y1) = points[(i + 1) % points.len()];
+      self.line(x0, y0, x1, y1, color);
+    }
+  }
+
+  /// Replace the color of the pixel at `(x, y)`, and of every pixel
+  /// of the same color connected to it horizontally or vertically,
+  /// with `color`
+  pub fn flood_fill(&mut self, x: isize, y: isize, color: Pixel) {
+    let target = match self.get(x, y) {
+      Some(target) if target != color => target,
+      _ => return,
+    };
+
+    let mut stack = vec![(x, y)];
+
+    while let Some((x, y)) = stack.pop() {
+      if self.get(x, y) != Some(target) {
+        continue;
+      }
+
+      // Fill the whole run of matching pixels in this row, and
+      // queue the pixels above and below it
+      let mut left = x;
+      while self.get(left - 1, y) == Some(target) {
+        left -= 1;
+      }
+
+      let mut right = x;
+      while self.get(right + 1, y) == Some(target) {
+        right += 1;
+      }
+
+      self.span(left, right, y, color);
+
+      for column in left..=right {
+        stack.push((column, y - 1));
+        stack.push((column, y + 1));
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use rgb;
+
+  /// Draw on a blank canvas with dimensions `resolution`, and
+  /// return its rows, with `#` for white pixels and `.` for
+  /// black pixels
+  fn draw(resolution: (usize, usize), f: impl Fn(&mut Canvas, Pixel)) -> Vec<String> {
+    let black = rgb(0.0, 0.0, 0.0);
+    let white = rgb(1.0, 1.0, 1.0);
+
+    let mut pixels = vec![black; resolution.0 * resolution.1];
+    f(&mut Canvas::new(&mut pixels, resolution), white);
+
+    pixels
+      .chunks(resolution.0)
+      .map(|row| {
+        row
+          .iter()
+          .map(|pixel| if *pixel == white { '#' } else { '.' })
+          .collect()
+      })
+      .collect()
+  }
+
+  #[test]
+  fn set_and_get() {
+    let mut pixels = vec![rgb(0.0, 0.0, 0.0); 4];
+    let mut canvas = Canvas::new(&mut pixels, (2, 2));
+    canvas.set(1, 0, rgb(1.0, 0.0, 0.0));
+    canvas.set(2, 0, rgb(1.0, 0.0, 0.0));
+    canvas.set(-1, 0, rgb(1.0, 0.0, 0.0));
+    assert_eq!(canvas.get(1, 0), Some(rgb(1.0, 0.0, 0.0)));
+    assert_eq!(canvas.get(0, 1), Some(rgb(0.0, 0.0, 0.0)));
+    assert_eq!(canvas.get(2, 0), None);
+    assert_eq!(canvas.get(0, -1), None);
+  }
+
+  #[test]
+  fn line() {
+    assert_eq!(
+      draw((6, 4), |canvas, color| canvas.line(0, 0, 5, 3, color)),
+      ["#.....", ".##...", "...##.", ".....#"]
+    );
+    assert_eq!(
+      draw((3, 3), |canvas, color| canvas.line(1, 5, 1, -5, color)),
+      [".#.", ".#.", ".#."]
+    );
+  }
+
+  #[test]
+  fn rect() {
+    assert_eq!(
+      draw((5, 4), |canvas, color| canvas.rect(1, 0, 4, 3, color)),
+      [".####", ".#..#", ".####", "....."]
+    );
+    assert_eq!(
+      draw((5, 4), |canvas, color| canvas.fill_rect(-1, 2, 3, 5, color)),
+      [".....", ".....", "##...", "##..."]
+    );
+  }
+
+  #[test]
+  fn rect_off_canvas() {
+    assert_eq!(
+      draw((5, 4), |canvas, color| canvas.rect(-1, -1, 4, 3, color)),
+      ["..#..", "###..", ".....", "....."]
+    );
+    assert_eq!(
+      draw((5, 4), |canvas, color| {
+        canvas.rect(-2, 1, usize::max_value(), 2, color)
+      }),
+      [".....", "#####", "#####", "....."]
+    );
+    assert_eq!(
+      draw((5, 4), |canvas, color| {
+        canvas.fill_rect(1, 1, usize::max_value(), usize::max_value(), color)
+      }),
+      [".....", ".####", ".####", ".####"]
+    );
+    assert_eq!(
+      draw((5, 4), |canvas, color| {
+        canvas.fill_rect(isize::min_value(), 2, usize::max_value(), 1, color)
+      }),
+      [".....", ".....", ".....", "....."]
+    );
+  }
+
+  #[test]
+  fn line_off_canvas() {
+    assert_eq!(
+      draw((3, 3), |canvas, color| {
+        canvas.line(isize::min_value(), 1, isize::max_value(), 1, color)
+      }),
+      ["...", "###", "..."]
+    );
+    assert_eq!(
+      draw((3, 3), |canvas, color| {
+        canvas.line(2, isize::max_value(), 2, isize::min_value(), color)
+      }),
+      ["..#", "..#", "..#"]
+    );
+    assert_eq!(
+      draw((3, 3), |canvas, color| {
+        canvas.line(-1_000_000_000_000, -1, 1_000_000_000_000, -1, color)
+      }),
+      ["...", "...", "..."]
+    );
+  }
+
+  #[test]
+  fn circle() {
+    assert_eq!(
+      draw((7, 7), |canvas, color| canvas.circle(3, 3, 3, color)),
+      [
+        "..###..",
+        ".#...#.",
+        "#.....#",
+        "#.....#",
+        "#.....#",
+        ".#...#.",
+        "..###..",
+      ]
+    );
+    assert_eq!(
+      draw((7, 7), |canvas, color| canvas.fill_circle(3, 3, 3, color)),
+      [
+        "..###..",
+        ".#####.",
+        "#######",
+        "#######",
+        "#######",
+        ".#####.",
+        "..###..",
+      ]
+    );
+  }
+
+  #[test]
+  fn polygon() {
+    assert_eq!(
+      draw((5, 5), |canvas, color| canvas.polygon(&[(0, 0), (4, 0), (0, 4)], color)),
+      ["#####", "#..#.", "#.#..", "##...", "#...."]
+    );
+  }
+
+  #[test]
+  fn flood_fill() {
+    assert_eq!(
+      draw((6, 5), |canvas, color| {
+        canvas.rect(0, 0, 4, 4, color);
+        canvas.flood_fill(4, 4, color);
+      }),
+      ["######", "#..###", "#..###", "######", "######"]
+    );
+  }
+}
@@ -0,0 +1,355 @@
+//! Color math and conversions
+
+use std::ops::{Add, Mul, Sub};
+
+use {rgba, Pixel};
+
+/// Convert a component to a byte, clamping it to `[0.0, 1.0]`
+fn byte(component: f32) -> u8 {
+  (component.max(0.0).min(1.0) * 255.0).round() as u8
+}
+
+/// Construct a pixel from the components of an RGB color cube
+/// rotated by `hue`, scaled by `chroma`, and raised by `minimum`
+fn hue_chroma(hue: f32, chroma: f32, minimum: f32) -> Pixel {
+  let sector = (hue % 360.0 + 360.0) % 360.0 / 60.0;
+  let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+
+  let (red, green, blue) = match sector as u32 {
+    0 => (chroma, x, 0.0),
+    1 => (x, chroma, 0.0),
+    2 => (0.0, chroma, x),
+    3 => (0.0, x, chroma),
+    4 => (x, 0.0, chroma),
+    _ => (chroma, 0.0, x),
+  };
+
+  rgba(red + minimum, green + minimum, blue + minimum, 1.0)
+}
+
+/// Construct a pixel from hue, saturation, and value components,
+/// with an alpha component of 1.0
+///
+/// `hue` is in degrees, and `saturation` and `value` are between
+/// `0.0` and `1.0` inclusive.
+pub fn hsv(hue: f32, saturation: f32, value: f32) -> Pixel {
+  let chroma = value * saturation;
+  hue_chroma(hue, chroma, value - chroma)
+}
+
+/// Construct a pixel from hue, saturation, and lightness components,
+/// with an alpha component of 1.0
+///
+/// `hue` is in degrees, and `saturation` and `lightness` are between
+/// `0.0` and `1.0` inclusive.
+pub fn hsl(hue: f32, saturation: f32, lightness: f32) -> Pixel {
+  let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+  hue_chroma(hue, chroma, lightness - chroma / 2.0)
+}
+
+impl Pixel {
+  /// Linearly interpolate between `self` and `other`, returning
+  /// `self` when `t` is 0.0 and `other` when `t` is 1.0
+  pub fn lerp(self, other: Pixel, t: f32) -> Pixel {
+    self + (other - self) * t
+  }
+
+  /// Clamp every component to `[0.0, 1.0]`
+  pub fn clamp(self) -> Pixel {
+    let clamp = |component: f32| component.max(0.0).min(1.0);
+    rgba(
+      clamp(self.red),
+      clamp(self.green),
+      clamp(self.blue),
+      clamp(self.alpha),
+    )
+  }
+
+  /// Multiply the color components by alpha
+  pub fn premultiply(self) -> Pixel {
+    rgba(
+      self.red * self.alpha,
+      self.green * self.alpha,
+      self.blue * self.alpha,
+      self.alpha,
+    )
+  }
+
+  /// Divide the color components by alpha, undoing `premultiply`.
+  /// Fully transparent pixels become transparent black.
+  pub fn unpremultiply(self) -> Pixel {
+    if self.alpha <= 0.0 {
+      return rgba(0.0, 0.0, 0.0, 0.0);
+    }
+
+    rgba(
+      self.red / self.alpha,
+      self.green / self.alpha,
+      self.blue / self.alpha,
+      self.alpha,
+    )
+  }
+
+  /// The relative luminance of the color, using the Rec. 709
+  /// coefficients. Alpha is ignored.
+  pub fn luminance(self) -> f32 {
+    0.2126 * self.red + 0.7152 * self.green + 0.0722 * self.blue
+  }
+
+  /// Composite `self` over `below` with the Porter-Duff over
+  /// operator. Both pixels, and the result, use straight alpha.
+  pub fn over(self, below: Pixel) -> Pixel {
+    let above = self.premultiply();
+    let below = below.premultiply();
+    (above + below * (1.0 - above.alpha)).unpremultiply()
+  }
+
+  /// Parse a hexadecimal color of the form `#rgb`, `#rgba`,
+  /// `#rrggbb`, or `#rrggbbaa`. The leading `#` is optional.
+  /// Colors without an alpha component are opaque.
+  pub fn from_hex(hex: &str) -> Option<Pixel> {
+    let hex = if hex.starts_with('#') { &hex[1..] } else { hex };
+
+    if !hex.is_ascii() {
+      return None;
+    }
+
+    let digits = match hex.len() {
+      3 | 4 => hex
+        .chars()
+        .map(|digit| digit.to_digit(16).map(|value| value as u8 * 17))
+        .collect::<Option<Vec<u8>>>()?,
+      6 | 8 => (0..hex.len() / 2)
+        .map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok())
+        .collect::<Option<Vec<u8>>>()?,
+      _ => return None,
+    };
+
+    let alpha = digits.get(3).cloned().unwrap_or(255);
+
+    Some(Pixel::from([digits[0], digits[1], digits[2], alpha]))
+  }
+
+  /// Format the pixel as a hexadecimal color of the form
+  /// `#rrggbbaa`, clamping components to `[0.0, 1.0]`
+  pub fn to_hex(self) -> String {
+    format!("#{:08x}", u32::from(self))
+  }
+}
+
+impl Add for Pixel {
+  type Output = Pixel;
+
+  /// Add each component
+  fn add(self, other: Pixel) -> Pixel {
+    rgba(
+      self.red + other.red,
+      self.green + other.green,
+      self.blue + other.blue,
+      self.alpha + other.alpha,
+    )
+  }
+}
+
+impl Sub for Pixel {
+  type Output = Pixel;
+
+  /// Subtract each component
+  fn sub(self, other: Pixel) -> Pixel {
+    rgba(
+      self.red - other.red,
+      self.green - other.green,
+      self.blue - other.blue,
+      self.alpha - other.alpha,
+    )
+  }
+}
+
+impl Mul<f32> for Pixel {
+  type Output = Pixel;
+
+  /// Multiply each component by `factor`
+  fn mul(self, factor: f32) -> Pixel {
+    rgba(
+      self.red * factor,
+      self.green * factor,
+      self.blue * factor,
+      self.alpha * factor,
+    )
+  }
+}
+
+impl Mul for Pixel {
+  type Output = Pixel;
+
+  /// Multiply each component by the corresponding component of `other`
+  fn mul(self, other: Pixel) -> Pixel {
+    rgba(
+      self.red * other.red,
+      self.green * other.green,
+      self.blue * other.blue,
+      self.alpha * other.alpha,
+    )
+  }
+}
+
+/// Convert from bytes in RGBA order
+impl From<[u8; 4]> for Pixel {
+  fn from(bytes: [u8; 4]) -> Pixel {
+    rgba(
+      f32::from(bytes[0]) / 255.0,
+      f32::from(bytes[1]) / 255.0,
+      f32::from(bytes[2]) / 255.0,
+      f32::from(bytes[3]) / 255.0,
+    )
+  }
+}
+
+/// Convert to bytes in RGBA order, clamping components to `[0.0, 1.0]`
+impl From<Pixel> for [u8; 4] {
+  fn from(pixel: Pixel) -> [u8; 4] {
+    [
+      byte(pixel.red),
+      byte(pixel.green),
+      byte(pixel.blue),
+      byte(pixel.alpha),
+    ]
+  }
+}
+
+/// Convert from a packed `0xRRGGBBAA` value
+impl From<u32> for Pixel {
+  fn from(value: u32) -> Pixel {
+    Pixel::from([
+      (value >> 24) as u8,
+      (value >> 16) as u8,
+      (value >> 8) as u8,
+      value as u8,
+    ])
+  }
+}
+
+/// Convert to a packed `0xRRGGBBAA` value, clamping components
+/// to `[0.0, 1.0]`
+impl From<Pixel> for u32 {
+  fn from(pixel: Pixel) -> u32 {
+    let [red, green, blue, alpha] = <[u8; 4]>::from(pixel);
+    u32::from(red) << 24 | u32::from(green) << 16 | u32::from(blue) << 8 | u32::from(alpha)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use rgb;
+
+  fn assert_close(actual: Pixel, expected: Pixel) {
+    let difference = actual - expected;
+    assert!(
+      [
+        difference.red,
+        difference.green,
+        difference.blue,
+        difference.alpha,
+      ].iter()
+        .all(|component| component.abs() < 1e-5),
+      "{:?} != {:?}",
+      actual,
+      expected
+    );
+  }
+
+  #[test]
+  fn operators() {
+    let a = rgba(0.5, 0.25, 1.0, 1.0);
+    let b = rgba(0.25, 0.25, 0.5, 0.5);
+    assert_eq!(a + b, rgba(0.75, 0.5, 1.5, 1.5));
+    assert_eq!(a - b, rgba(0.25, 0.0, 0.5, 0.5));
+    assert_eq!(a * 2.0, rgba(1.0, 0.5, 2.0, 2.0));
+    assert_eq!(a * b, rgba(0.125, 0.0625, 0.5, 0.5));
+  }
+
+  #[test]
+  fn lerp_and_clamp() {
+    let a = rgb(0.0, 0.5, 1.0);
+    let b = rgb(1.0, 0.5, 0.0);
+    assert_eq!(a.lerp(b, 0.0), a);
+    assert_eq!(a.lerp(b, 1.0), b);
+    assert_eq!(a.lerp(b, 0.25), rgb(0.25, 0.5, 0.75));
+    assert_eq!(
+      rgba(-1.0, 0.5, 2.0, 1.5).clamp(),
+      rgba(0.0, 0.5, 1.0, 1.0)
+    );
+  }
+
+  #[test]
+  fn conversions() {
+    let pixel = Pixel::from(0xff80_0040);
+    assert_close(pixel, rgba(1.0, 128.0 / 255.0, 0.0, 64.0 / 255.0));
+    assert_eq!(u32::from(pixel), 0xff80_0040);
+    assert_eq!(<[u8; 4]>::from(pixel), [255, 128, 0, 64]);
+    assert_eq!(Pixel::from([255, 128, 0, 64]), pixel);
+    assert_eq!(u32::from(rgba(2.0, -1.0, 0.0, 1.0)), 0xff00_00ff);
+  }
+
+  #[test]
+  fn hex() {
+    assert_eq!(Pixel::from_hex("#ff8000"), Some(Pixel::from(0xff80_00ff)));
+    assert_eq!(Pixel::from_hex("ff800040"), Some(Pixel::from(0xff80_0040)));
+    assert_eq!(Pixel::from_hex("#f80"), Some(Pixel::from(0xff88_00ff)));
+    assert_eq!(Pixel::from_hex("#f804"), Some(Pixel::from(0xff88_0044)));
+    assert_eq!(Pixel::from_hex("#ff80"), Some(Pixel::from(0xffff_8800)));
+    assert_eq!(Pixel::from_hex("#ff800"), None);
+    assert_eq!(Pixel::from_hex("#gg8000"), None);
+    assert_eq!(Pixel::from_hex("#ff☃0"), None);
+    assert_eq!(Pixel::from_hex("##ff8000"), None);
+    assert_eq!(Pixel::from(0xff80_0040).to_hex(), "#ff800040");
+  }
+
+  #[test]
+  fn hsv_and_hsl() {
+    assert_close(hsv(0.0, 1.0, 1.0), rgb(1.0, 0.0, 0.0));
+    assert_close(hsv(120.0, 1.0, 1.0), rgb(0.0, 1.0, 0.0));
+    assert_close(hsv(240.0, 0.5, 1.0), rgb(0.5, 0.5, 1.0));
+    assert_close(hsv(-60.0, 1.0, 0.5), rgb(0.5, 0.0, 0.5));
+    assert_close(hsv(30.0, 0.0, 0.25), rgb(0.25, 0.25, 0.25));
+    assert_close(hsl(0.0, 1.0, 0.5), rgb(1.0, 0.0, 0.0));
+    assert_close(hsl(180.0, 1.0, 0.25), rgb(0.0, 0.5, 0.5));
+    assert_close(hsl(60.0, 0.5, 0.75), rgb(0.875, 0.875, 0.625));
+    assert_close(hsl(300.0, 1.0, 1.0), rgb(1.0, 1.0, 1.0));
+  }
+
+  #[test]
+  fn alpha() {
+    let pixel = rgba(1.0, 0.5, 0.0, 0.5);
+    assert_eq!(pixel.premultiply(), rgba(0.5, 0.25, 0.0, 0.5));
+    assert_eq!(pixel.premultiply().unpremultiply(), pixel);
+    assert_eq!(
+      rgba(1.0, 1.0, 1.0, 0.0).unpremultiply(),
+      rgba(0.0, 0.0, 0.0, 0.0)
+    );
+  }
+
+  #[test]
+  fn luminance() {
+    assert_eq!(rgb(0.0, 0.0, 0.0).luminance(), 0.0);
+    assert!((rgb(1.0, 1.0, 1.0).luminance() - 1.0).abs() < 1e-6);
+    assert!(rgb(0.0, 1.0, 0.0).luminance() > rgb(1.0, 0.0, 0.0).luminance());
+  }
+
+  #[test]
+  fn over() {
+    let background = rgb(0.0, 0.5, 1.0);
+    assert_eq!(rgb(1.0, 0.0, 0.0).over(background), rgb(1.0, 0.0, 0.0));
+    assert_eq!(rgba(1.0, 0.0, 0.0, 0.0).over(background), background);
+    assert_close(
+      rgba(1.0, 0.5, 0.0, 0.5).over(background),
+      rgb(0.5, 0.5, 0.5),
+    );
+    assert_close(
+      rgba(1.0, 0.0, 0.0, 0.5).over(rgba(0.0, 0.0, 1.0, 0.5)),
+      rgba(2.0 / 3.0, 0.0, 1.0 / 3.0, 0.75),
+    );
+  }
+}
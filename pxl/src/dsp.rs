@@ -0,0 +1,659 @@
+//! Building blocks for synthesizers: oscillators, envelopes, filters
+//! and effects, all running at `SAMPLES_PER_SECOND`
+
+use std::{f32::consts::PI, f64};
+
+use audio::sample_count;
+use {AudioSample, Duration, SAMPLES_PER_SECOND};
+
+/// A source of audio, producing one sample at a time
+pub trait Generator {
+  /// Produce the next sample
+  fn generate(&mut self) -> AudioSample;
+}
+
+/// An audio effect, transforming one sample at a time
+pub trait Processor {
+  /// Transform `input`, returning the output sample
+  fn process(&mut self, input: AudioSample) -> AudioSample;
+}
+
+/// A generator followed by a processor, which is itself a generator
+impl<G: Generator, P: Processor> Generator for (G, P) {
+  fn generate(&mut self) -> AudioSample {
+    let sample = self.0.generate();
+    self.1.process(sample)
+  }
+}
+
+/// Two processors, one after the other, which are themselves a
+/// processor
+impl<A: Processor, B: Processor> Processor for (A, B) {
+  fn process(&mut self, input: AudioSample) -> AudioSample {
+    let sample = self.0.process(input);
+    self.1.process(sample)
+  }
+}
+
+fn mono(value: f32) -> AudioSample {
+  AudioSample {
+    left: value,
+    right: value,
+  }
+}
+
+fn scale(sample: AudioSample, factor: f32) -> AudioSample {
+  AudioSample {
+    left: sample.left * factor,
+    right: sample.right * factor,
+  }
+}
+
+/// Correction for a step of height -2 at `t`, with the oscillator
+/// advancing by `dt` per sample
+fn poly_blep(t: f32, dt: f32) -> f32 {
+  if t < dt {
+    let x = t / dt;
+    x + x - x * x - 1.0
+  } else if t > 1.0 - dt {
+    let x = (t - 1.0) / dt;
+    x * x + x + x + 1.0
+  } else {
+    0.0
+  }
+}
+
+/// Correction for a change in slope of 1 per sample at `t`, with the
+/// oscillator advancing by `dt` per sample
+fn poly_blamp(t: f32, dt: f32) -> f32 {
+  if t < dt {
+    let x = t / dt - 1.0;
+    -x * x * x / 3.0
+  } else if t > 1.0 - dt {
+    let x = (t - 1.0) / dt + 1.0;
+    x * x * x / 3.0
+  } else {
+    0.0
+  }
+}
+
+/// The shape of an oscillator's output
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Waveform {
+  /// A pure tone
+  Sine,
+  /// High for the first half of each period, and low for the second
+  Square,
+  /// Rising from low to high over each period
+  Saw,
+  /// Rising and falling linearly, with the same phase as `Sine`
+  Triangle,
+}
+
+/// A band-limited oscillator
+///
+/// The discontinuities of square, saw, and triangle waves are smoothed
+/// with PolyBLEP and PolyBLAMP corrections, which greatly reduces
+/// aliasing. Output is between -1.0 and 1.0, and the same in both
+/// channels.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Oscillator {
+  /// The shape of the output
+  pub waveform: Waveform,
+  /// The frequency in hertz, which should be less than half of
+  /// `SAMPLES_PER_SECOND`
+  pub frequency: f32,
+  phase: f32,
+}
+
+impl Oscillator {
+  /// Create a new oscillator, starting at the beginning of its period
+  pub fn new(waveform: Waveform, frequency: f32) -> Oscillator {
+    Oscillator {
+      phase: 0.0,
+      waveform,
+      frequency,
+    }
+  }
+
+  /// Return to the beginning of the period
+  pub fn reset(&mut self) {
+    self.phase = 0.0;
+  }
+}
+
+impl Generator for Oscillator {
+  fn generate(&mut self) -> AudioSample {
+    let t = self.phase;
+    let dt = self.frequency / SAMPLES_PER_SECOND as f32;
+
+    let value = match self.waveform {
+      Waveform::Sine => (t * 2.0 * PI).sin(),
+      Waveform::Square => {
+        let naive = if t < 0.5 { 1.0 } else { -1.0 };
+        naive + poly_blep(t, dt) - poly_blep((t + 0.5).fract(), dt)
+      }
+      Waveform::Saw => 2.0 * t - 1.0 - poly_blep(t, dt),
+      Waveform::Triangle => {
+        let naive = 1.0 - 4.0 * ((t + 0.25).fract() - 0.5).abs();
+        // The slope changes by -8 per period at the peak and by 8 at
+        // the trough
+        naive - 8.0 * dt * poly_blamp((t + 0.75).fract(), dt)
+          + 8.0 * dt * poly_blamp((t + 0.25).fract(), dt)
+      }
+    };
+
+    self.phase += dt;
+    self.phase -= self.phase.floor();
+
+    mono(value)
+  }
+}
+
+/// A white noise generator, producing uniformly distributed values
+/// between -1.0 and 1.0 from a xorshift random number generator
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Noise {
+  state: u32,
+}
+
+impl Noise {
+  /// Create a new noise generator. Generators with the same `seed`
+  /// produce the same output.
+  pub fn new(seed: u32) -> Noise {
+    // Xorshift never leaves a state of zero
+    Noise {
+      state: if seed == 0 { 0x9E37_79B9 } else { seed },
+    }
+  }
+}
+
+impl Default for Noise {
+  fn default() -> Noise {
+    Noise::new(0)
+  }
+}
+
+impl Generator for Noise {
+  fn generate(&mut self) -> AudioSample {
+    self.state ^= self.state << 13;
+    self.state ^= self.state >> 17;
+    self.state ^= self.state << 5;
+
+    // The top 24 bits fit exactly in an f32
+    mono((self.state >> 8) as f32 / (1 << 23) as f32 - 1.0)
+  }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Stage {
+  Idle,
+  Attack,
+  Decay,
+  Sustain,
+  Release,
+}
+
+/// An attack, decay, sustain, release envelope
+///
+/// When triggered, the envelope's level rises linearly from its
+/// current level to 1.0 over `attack`, falls to `sustain` over
+/// `decay`, and stays there until released, after which it falls to
+/// 0.0 over `release`. As a processor, it multiplies its input by its
+/// level, advancing by one sample each time.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Envelope {
+  /// The time taken to rise to full level when triggered
+  pub attack: Duration,
+  /// The time taken to fall from full level to `sustain`
+  pub decay: Duration,
+  /// The level held until the envelope is released
+  pub sustain: f32,
+  /// The time taken to fall to zero when released
+  pub release: Duration,
+  stage: Stage,
+  start: f32,
+  position: usize,
+  level: f32,
+}
+
+impl Envelope {
+  /// Create a new envelope, which is silent until triggered
+  pub fn new(attack: Duration, decay: Duration, sustain: f32, release: Duration) -> Envelope {
+    Envelope {
+      stage: Stage::Idle,
+      start: 0.0,
+      position: 0,
+      level: 0.0,
+      attack,
+      decay,
+      sustain,
+      release,
+    }
+  }
+
+  fn enter(&mut self, stage: Stage) {
+    self.stage = stage;
+    self.start = self.level;
+    self.position = 0;
+  }
+
+  /// Start the attack, as when a note is pressed
+  pub fn trigger(&mut self) {
+    self.enter(Stage::Attack);
+  }
+
+  /// Start the release, as when a note is lifted. Does nothing if the
+  /// envelope is already released or finished.
+  pub fn release(&mut self) {
+    if self.stage != Stage::Idle && self.stage != Stage::Release {
+      self.enter(Stage::Release);
+    }
+  }
+
+  /// The current level, between 0.0 and 1.0
+  pub fn level(&self) -> f32 {
+    self.level
+  }
+
+  /// Whether the envelope has finished releasing, or was never
+  /// triggered
+  pub fn is_finished(&self) -> bool {
+    self.stage == Stage::Idle
+  }
+
+  /// Advance one sample through the current stage, towards `target`
+  fn segment(&mut self, target: f32, duration: Duration, next: Stage) {
+    let length = sample_count(duration);
+    self.position += 1;
+    if self.position >= length {
+      self.level = target;
+      self.enter(next);
+    } else {
+      self.level = self.start + (target - self.start) * self.position as f32 / length as f32;
+    }
+  }
+
+  /// Advance by one sample, returning the new level
+  pub fn next_level(&mut self) -> f32 {
+    match self.stage {
+      Stage::Idle => {}
+      Stage::Attack => self.segment(1.0, self.attack, Stage::Decay),
+      Stage::Decay => self.segment(self.sustain, self.decay, Stage::Sustain),
+      Stage::Sustain => self.level = self.sustain,
+      Stage::Release => self.segment(0.0, self.release, Stage::Idle),
+    }
+    self.level
+  }
+}
+
+impl Processor for Envelope {
+  fn process(&mut self, input: AudioSample) -> AudioSample {
+    let level = self.next_level();
+    scale(input, level)
+  }
+}
+
+/// The response of a biquad filter
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FilterKind {
+  /// Pass frequencies below the cutoff
+  LowPass,
+  /// Pass frequencies above the cutoff
+  HighPass,
+  /// Pass frequencies around the center, with a peak gain of 1.0
+  BandPass,
+}
+
+/// A second-order filter, with coefficients from Robert
+/// Bristow-Johnson's Audio EQ Cookbook
+///
+/// A `q` of `FRAC_1_SQRT_2` gives low-pass and high-pass filters a
+/// flat response with no resonance. Higher values resonate at the
+/// cutoff, and for band-pass filters, narrow the band.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Biquad {
+  b0: f64,
+  b1: f64,
+  b2: f64,
+  a1: f64,
+  a2: f64,
+  left: [f64; 2],
+  right: [f64; 2],
+}
+
+impl Biquad {
+  /// Create a new filter of `kind` with cutoff or center `frequency`
+  /// in hertz, which should be less than half of `SAMPLES_PER_SECOND`,
+  /// and quality factor `q`, which must be greater than zero
+  pub fn new(kind: FilterKind, frequency: f32, q: f32) -> Biquad {
+    let mut biquad = Biquad {
+      b0: 0.0,
+      b1: 0.0,
+      b2: 0.0,
+      a1: 0.0,
+      a2: 0.0,
+      left: [0.0; 2],
+      right: [0.0; 2],
+    };
+    biquad.set(kind, frequency, q);
+    biquad
+  }
+
+  /// Change the filter's parameters, keeping its state, so it can be
+  /// swept smoothly
+  pub fn set(&mut self, kind: FilterKind, frequency: f32, q: f32) {
+    let w0 = 2.0 * f64::consts::PI * f64::from(frequency) / f64::from(SAMPLES_PER_SECOND);
+    let (sin, cos) = w0.sin_cos();
+    let alpha = sin / (2.0 * f64::from(q));
+
+    let (b0, b1, b2) = match kind {
+      FilterKind::LowPass => ((1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0),
+      FilterKind::HighPass => ((1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0),
+      FilterKind::BandPass => (alpha, 0.0, -alpha),
+    };
+
+    let a0 = 1.0 + alpha;
+    self.b0 = b0 / a0;
+    self.b1 = b1 / a0;
+    self.b2 = b2 / a0;
+    self.a1 = -2.0 * cos / a0;
+    self.a2 = (1.0 - alpha) / a0;
+  }
+
+  /// Filter one channel, in transposed direct form II. Coefficients
+  /// and state are kept in double precision, since low cutoffs are
+  /// very inaccurate in single precision.
+  fn filter(&self, state: &mut [f64; 2], x: f32) -> f32 {
+    let x = f64::from(x);
+    let y = self.b0 * x + state[0];
+    state[0] = self.b1 * x - self.a1 * y + state[1];
+    state[1] = self.b2 * x - self.a2 * y;
+    y as f32
+  }
+}
+
+impl Processor for Biquad {
+  fn process(&mut self, input: AudioSample) -> AudioSample {
+    let (mut left, mut right) = (self.left, self.right);
+    let output = AudioSample {
+      left: self.filter(&mut left, input.left),
+      right: self.filter(&mut right, input.right),
+    };
+    self.left = left;
+    self.right = right;
+    output
+  }
+}
+
+/// A feedback delay, or echo
+#[derive(Clone, Debug, PartialEq)]
+pub struct Delay {
+  /// The proportion of each echo fed back into the delay, from 0.0
+  /// for a single echo to just below 1.0 for echoes that take a long
+  /// time to fade
+  pub feedback: f32,
+  /// The proportion of delayed signal in the output, from 0.0 for
+  /// only the input to 1.0 for only the echoes
+  pub mix: f32,
+  buffer: Vec<AudioSample>,
+  position: usize,
+}
+
+impl Delay {
+  /// Create a new delay with echoes `time` apart. Delays shorter than
+  /// one sample are lengthened to one sample.
+  pub fn new(time: Duration, feedback: f32, mix: f32) -> Delay {
+    Delay {
+      buffer: vec![AudioSample::default(); sample_count(time).max(1)],
+      position: 0,
+      feedback,
+      mix,
+    }
+  }
+}
+
+impl Processor for Delay {
+  fn process(&mut self, input: AudioSample) -> AudioSample {
+    let delayed = self.buffer[self.position];
+
+    self.buffer[self.position] = AudioSample {
+      left: input.left + delayed.left * self.feedback,
+      right: input.right + delayed.right * self.feedback,
+    };
+    self.position = (self.position + 1) % self.buffer.len();
+
+    AudioSample {
+      left: input.left * (1.0 - self.mix) + delayed.left * self.mix,
+      right: input.right * (1.0 - self.mix) + delayed.right * self.mix,
+    }
+  }
+}
+
+/// Multiplies both channels by `gain`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Gain {
+  /// The factor applied to the input, where 1.0 leaves it unchanged
+  pub gain: f32,
+}
+
+impl Gain {
+  /// Create a new gain processor
+  pub fn new(gain: f32) -> Gain {
+    Gain { gain }
+  }
+}
+
+impl Processor for Gain {
+  fn process(&mut self, input: AudioSample) -> AudioSample {
+    scale(input, self.gain)
+  }
+}
+
+/// Adjusts the balance between the left and right channels
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Pan {
+  /// From -1.0 for only the left channel, through 0.0 for both
+  /// channels unchanged, to 1.0 for only the right channel
+  pub pan: f32,
+}
+
+impl Pan {
+  /// Create a new pan processor
+  pub fn new(pan: f32) -> Pan {
+    Pan { pan }
+  }
+
+  /// The gains applied to the left and right channels
+  pub fn gains(&self) -> (f32, f32) {
+    let pan = self.pan.max(-1.0).min(1.0);
+    ((1.0 - pan).min(1.0), (1.0 + pan).min(1.0))
+  }
+}
+
+impl Processor for Pan {
+  fn process(&mut self, input: AudioSample) -> AudioSample {
+    let (left, right) = self.gains();
+    AudioSample {
+      left: input.left * left,
+      right: input.right * right,
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn generate(generator: &mut impl Generator, count: usize) -> Vec<f32> {
+    (0..count).map(|_| generator.generate().left).collect()
+  }
+
+  fn assert_close(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+    for (a, e) in actual.iter().zip(expected) {
+      assert!((a - e).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+    }
+  }
+
+  /// A duration of `samples` samples
+  fn samples(samples: u32) -> Duration {
+    Duration::new(0, samples * (1_000_000_000 / SAMPLES_PER_SECOND))
+  }
+
+  fn impulse(processor: &mut impl Processor, count: usize) -> Vec<f32> {
+    (0..count)
+      .map(|i| {
+        let input = mono(if i == 0 { 1.0 } else { 0.0 });
+        processor.process(input).left
+      })
+      .collect()
+  }
+
+  #[test]
+  fn oscillators() {
+    let mut sine = Oscillator::new(Waveform::Sine, 12_000.0);
+    assert_close(&generate(&mut sine, 4), &[0.0, 1.0, 0.0, -1.0]);
+
+    // 18 kHz advances the phase by 3/8 each sample, landing on both
+    // sides of each discontinuity
+    let mut square = Oscillator::new(Waveform::Square, 18_000.0);
+    assert_close(
+      &generate(&mut square, 8),
+      &[
+        0.0, 0.555_556, -0.777_778, 0.555_556, 0.0, -0.555_556, 0.777_778, -0.555_556,
+      ],
+    );
+
+    let mut saw = Oscillator::new(Waveform::Saw, 18_000.0);
+    assert_close(
+      &generate(&mut saw, 8),
+      &[
+        0.0, -0.25, 0.388_889, -0.305_556, 0.0, 0.305_556, -0.388_889, 0.25,
+      ],
+    );
+
+    let mut triangle = Oscillator::new(Waveform::Triangle, 18_000.0);
+    assert_close(
+      &generate(&mut triangle, 8),
+      &[
+        0.0, 0.203_704, 0.0, 0.203_704, 0.0, -0.203_704, 0.0, -0.203_704,
+      ],
+    );
+  }
+
+  #[test]
+  fn low_frequencies_are_unaltered() {
+    let mut saw = Oscillator::new(Waveform::Saw, 480.0);
+    let output = generate(&mut saw, 100);
+    for (i, value) in output.iter().enumerate().skip(1) {
+      assert!((value - (i as f32 / 50.0 - 1.0)).abs() < 1e-4);
+    }
+  }
+
+  #[test]
+  fn noise() {
+    let output = generate(&mut Noise::new(1), 10_000);
+    assert!(output.iter().all(|value| *value >= -1.0 && *value < 1.0));
+
+    let mean = output.iter().sum::<f32>() / output.len() as f32;
+    assert!(mean.abs() < 0.05);
+
+    assert_eq!(generate(&mut Noise::new(1), 10), &output[..10]);
+    assert_ne!(generate(&mut Noise::new(2), 10), &output[..10]);
+  }
+
+  #[test]
+  fn envelope() {
+    fn levels(envelope: &mut Envelope, count: usize) -> Vec<f32> {
+      (0..count).map(|_| envelope.next_level()).collect()
+    }
+
+    let mut envelope = Envelope::new(samples(4), samples(2), 0.5, samples(2));
+
+    assert_eq!(levels(&mut envelope, 2), [0.0, 0.0]);
+    envelope.trigger();
+    assert_eq!(
+      levels(&mut envelope, 8),
+      [0.25, 0.5, 0.75, 1.0, 0.75, 0.5, 0.5, 0.5]
+    );
+    envelope.release();
+    assert_eq!(levels(&mut envelope, 3), [0.25, 0.0, 0.0]);
+    assert!(envelope.is_finished());
+
+    envelope.trigger();
+    assert_eq!(levels(&mut envelope, 2), [0.25, 0.5]);
+    envelope.release();
+    assert_eq!(levels(&mut envelope, 2), [0.25, 0.0]);
+  }
+
+  #[test]
+  fn filters() {
+    let mut low_pass = Biquad::new(FilterKind::LowPass, 12_000.0, 0.707_107);
+    assert_close(
+      &impulse(&mut low_pass, 4),
+      &[0.292_893, 0.585_786, 0.242_641, -0.100_505],
+    );
+
+    let mut high_pass = Biquad::new(FilterKind::HighPass, 12_000.0, 0.707_107);
+    assert_close(
+      &impulse(&mut high_pass, 4),
+      &[0.292_893, -0.585_786, 0.242_641, 0.100_505],
+    );
+
+    let mut band_pass = Biquad::new(FilterKind::BandPass, 12_000.0, 1.0);
+    assert_close(
+      &impulse(&mut band_pass, 4),
+      &[0.333_333, 0.0, -0.444_444, 0.0],
+    );
+
+    // A low-pass filter passes a constant signal unaltered
+    let mut low_pass = Biquad::new(FilterKind::LowPass, 100.0, 0.707_107);
+    let mut output = AudioSample::default();
+    for _ in 0..SAMPLES_PER_SECOND {
+      output = low_pass.process(mono(1.0));
+    }
+    assert!((output.left - 1.0).abs() < 1e-4);
+  }
+
+  #[test]
+  fn delay() {
+    let mut delay = Delay::new(samples(2), 0.5, 0.5);
+    assert_close(
+      &impulse(&mut delay, 7),
+      &[0.5, 0.0, 0.5, 0.0, 0.25, 0.0, 0.125],
+    );
+  }
+
+  #[test]
+  fn gain_and_pan() {
+    let input = AudioSample {
+      left: 1.0,
+      right: 0.5,
+    };
+
+    let mut chain = (Gain::new(2.0), Pan::new(-0.5));
+    assert_eq!(
+      chain.process(input),
+      AudioSample {
+        left: 2.0,
+        right: 0.5,
+      }
+    );
+
+    assert_eq!(Pan::new(1.0).gains(), (0.0, 1.0));
+    assert_eq!(Pan::new(0.0).gains(), (1.0, 1.0));
+  }
+
+  #[test]
+  fn composition() {
+    let mut envelope = Envelope::new(samples(2), samples(0), 1.0, samples(0));
+    envelope.trigger();
+
+    let mut voice = (
+      Oscillator::new(Waveform::Square, 1_000.0),
+      (Gain::new(0.5), envelope),
+    );
+    assert_close(&generate(&mut voice, 3), &[0.0, 0.5, 0.5]);
+  }
+}
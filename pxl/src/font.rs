@@ -0,0 +1,484 @@
+//! Bitmap fonts and text drawing
+
+use {Canvas, Image, Pixel};
+
+/// Width of the built-in font's glyphs, in pixels
+const BUILTIN_WIDTH: usize = 5;
+
+/// Height of the built-in font's glyphs, in pixels
+const BUILTIN_HEIGHT: usize = 7;
+
+/// The built-in font's glyphs, for the printable ASCII characters
+/// from `' '` to `'~'`. Each glyph is a list of rows, from top to
+/// bottom, with the most significant of each row's five bits
+/// representing its leftmost pixel.
+static BUILTIN_GLYPHS: [[u8; BUILTIN_HEIGHT]; 95] = [
+  // ' '
+  [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000],
+  // '!'
+  [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100],
+  // '"'
+  [0b01010, 0b01010, 0b01010, 0b00000, 0b00000, 0b00000, 0b00000],
+  // '#'
+  [0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010],
+  // '$'
+  [0b00100, 0b01111, 0b10100, 0b01110, 0b00101, 0b11110, 0b00100],
+  // '%'
+  [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011],
+  // '&'
+  [0b01100, 0b10010, 0b10100, 0b01000, 0b10101, 0b10010, 0b01101],
+  // '\''
+  [0b00100, 0b00100, 0b00100, 0b00000, 0b00000, 0b00000, 0b00000],
+  // '('
+  [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010],
+  // ')'
+  [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000],
+  // '*'
+  [0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000],
+  // '+'
+  [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000],
+  // ','
+  [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000],
+  // '-'
+  [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000],
+  // '.'
+  [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100],
+  // '/'
+  [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000],
+  // '0'
+  [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+  // '1'
+  [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+  // '2'
+  [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+  // '3'
+  [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+  // '4'
+  [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+  // '5'
+  [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+  // '6'
+  [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+  // '7'
+  [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+  // '8'
+  [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+  // '9'
+  [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+  // ':'
+  [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000],
+  // ';'
+  [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b00100, 0b01000],
+  // '<'
+  [0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010],
+  // '='
+  [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000],
+  // '>'
+  [0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000],
+  // '?'
+  [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100],
+  // '@'
+  [0b01110, 0b10001, 0b00001, 0b01101, 0b10101, 0b10101, 0b01110],
+  // 'A'
+  [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+  // 'B'
+  [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+  // 'C'
+  [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+  // 'D'
+  [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100],
+  // 'E'
+  [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+  // 'F'
+  [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+  // 'G'
+  [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111],
+  // 'H'
+  [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+  // 'I'
+  [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+  // 'J'
+  [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100],
+  // 'K'
+  [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+  // 'L'
+  [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+  // 'M'
+  [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+  // 'N'
+  [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001],
+  // 'O'
+  [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+  // 'P'
+  [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000],
+  // 'Q'
+  [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101],
+  // 'R'
+  [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+  // 'S'
+  [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110],
+  // 'T'
+  [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+  // 'U'
+  [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+  // 'V'
+  [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+  // 'W'
+  [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010],
+  // 'X'
+  [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001],
+  // 'Y'
+  [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100],
+  // 'Z'
+  [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111],
+  // '['
+  [0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110],
+  // '\\'
+  [0b00000, 0b10000, 0b01000, 0b00100, 0b00010, 0b00001, 0b00000],
+  // ']'
+  [0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110],
+  // '^'
+  [0b00100, 0b01010, 0b10001, 0b00000, 0b00000, 0b00000, 0b00000],
+  // '_'
+  [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111],
+  // '`'
+  [0b01000, 0b00100, 0b00010, 0b00000, 0b00000, 0b00000, 0b00000],
+  // 'a'
+  [0b00000, 0b00000, 0b01110, 0b00001, 0b01111, 0b10001, 0b01111],
+  // 'b'
+  [0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b11110],
+  // 'c'
+  [0b00000, 0b00000, 0b01110, 0b10000, 0b10000, 0b10001, 0b01110],
+  // 'd'
+  [0b00001, 0b00001, 0b01101, 0b10011, 0b10001, 0b10001, 0b01111],
+  // 'e'
+  [0b00000, 0b00000, 0b01110, 0b10001, 0b11111, 0b10000, 0b01110],
+  // 'f'
+  [0b00110, 0b01001, 0b01000, 0b11100, 0b01000, 0b01000, 0b01000],
+  // 'g'
+  [0b00000, 0b01111, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110],
+  // 'h'
+  [0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001],
+  // 'i'
+  [0b00100, 0b00000, 0b01100, 0b00100, 0b00100, 0b00100, 0b01110],
+  // 'j'
+  [0b00010, 0b00000, 0b00110, 0b00010, 0b00010, 0b10010, 0b01100],
+  // 'k'
+  [0b10000, 0b10000, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010],
+  // 'l'
+  [0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+  // 'm'
+  [0b00000, 0b00000, 0b11010, 0b10101, 0b10101, 0b10001, 0b10001],
+  // 'n'
+  [0b00000, 0b00000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001],
+  // 'o'
+  [0b00000, 0b00000, 0b01110, 0b10001, 0b10001, 0b10001, 0b01110],
+  // 'p'
+  [0b00000, 0b00000, 0b11110, 0b10001, 0b11110, 0b10000, 0b10000],
+  // 'q'
+  [0b00000, 0b00000, 0b01101, 0b10011, 0b01111, 0b00001, 0b00001],
+  // 'r'
+  [0b00000, 0b00000, 0b10110, 0b11001, 0b10000, 0b10000, 0b10000],
+  // 's'
+  [0b00000, 0b00000, 0b01110, 0b10000, 0b01110, 0b00001, 0b11110],
+  // 't'
+  [0b01000, 0b01000, 0b11100, 0b01000, 0b01000, 0b01001, 0b00110],
+  // 'u'
+  [0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b10011, 0b01101],
+  // 'v'
+  [0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+  // 'w'
+  [0b00000, 0b00000, 0b10001, 0b10001, 0b10101, 0b10101, 0b01010],
+  // 'x'
+  [0b00000, 0b00000, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001],
+  // 'y'
+  [0b00000, 0b00000, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110],
+  // 'z'
+  [0b00000, 0b00000, 0b11111, 0b00010, 0b00100, 0b01000, 0b11111],
+  // '{'
+  [0b00010, 0b00100, 0b00100, 0b01000, 0b00100, 0b00100, 0b00010],
+  // '|'
+  [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+  // '}'
+  [0b01000, 0b00100, 0b00100, 0b00010, 0b00100, 0b00100, 0b01000],
+  // '~'
+  [0b00000, 0b00000, 0b01000, 0b10101, 0b00010, 0b00000, 0b00000],
+];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Glyphs<'glyphs> {
+  Builtin,
+  Sheet {
+    image: &'glyphs Image<'glyphs>,
+    first: char,
+  },
+}
+
+/// A fixed-width bitmap font
+///
+/// Glyphs are separated by one pixel horizontally, and lines by one
+/// pixel vertically. Characters that the font has no glyph for are
+/// drawn as blank space.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Font<'glyphs> {
+  glyph_width: usize,
+  glyph_height: usize,
+  glyphs: Glyphs<'glyphs>,
+}
+
+impl Font<'static> {
+  /// The built-in font, with 5x7 pixel glyphs for the
+  /// printable ASCII characters
+  pub fn builtin() -> Font<'static> {
+    Font {
+      glyph_width: BUILTIN_WIDTH,
+      glyph_height: BUILTIN_HEIGHT,
+      glyphs: Glyphs::Builtin,
+    }
+  }
+}
+
+impl<'glyphs> Font<'glyphs> {
+  /// Create a font from a glyph sheet: an image containing a grid
+  /// of glyphs of size `glyph_size`, in order from left to right
+  /// and top to bottom, starting with the glyph for `first`.
+  ///
+  /// Glyph pixels with an alpha of at least 0.5 are drawn in the
+  /// text's color, and the rest are left blank.
+  pub fn from_image(
+    image: &'glyphs Image<'glyphs>,
+    glyph_size: (usize, usize),
+    first: char,
+  ) -> Font<'glyphs> {
+    Font {
+      glyph_width: glyph_size.0,
+      glyph_height: glyph_size.1,
+      glyphs: Glyphs::Sheet { image, first },
+    }
+  }
+
+  /// The size of each glyph in pixels
+  pub fn glyph_size(&self) -> (usize, usize) {
+    (self.glyph_width, self.glyph_height)
+  }
+
+  /// Horizontal distance between the left edges of adjacent glyphs
+  fn advance(&self) -> usize {
+    self.glyph_width + 1
+  }
+
+  /// Vertical distance between the top edges of adjacent lines
+  fn line_height(&self) -> usize {
+    self.glyph_height + 1
+  }
+
+  /// Returns true if the pixel at `(x, y)` of the glyph for
+  /// `character` should be drawn
+  fn is_set(&self, character: char, x: usize, y: usize) -> bool {
+    match self.glyphs {
+      Glyphs::Builtin => match character {
+        ' '..='~' => {
+          BUILTIN_GLYPHS[character as usize - ' ' as usize][y] & (1 << (BUILTIN_WIDTH - 1 - x)) != 0
+        }
+        _ => false,
+      },
+      Glyphs::Sheet { image, first } => {
+        let columns = image.width / self.glyph_width.max(1);
+        if columns == 0 || character < first {
+          return false;
+        }
+        let index = character as usize - first as usize;
+        let left = index % columns * self.glyph_width + x;
+        let top = index / columns * self.glyph_height + y;
+        top < image.height && image.pixels[left + top * image.width].alpha >= 0.5
+      }
+    }
+  }
+
+  /// The width and height in pixels of `text` when drawn with
+  /// this font, taking newlines into account
+  pub fn measure(&self, text: &str) -> (usize, usize) {
+    let mut lines = 0;
+    let mut columns = 0;
+
+    for line in text.split('\n') {
+      lines += 1;
+      columns = columns.max(line.chars().count());
+    }
+
+    (
+      (columns * self.advance()).saturating_sub(1),
+      (lines * self.line_height()).saturating_sub(1),
+    )
+  }
+
+  /// Insert newlines into `text` so that no line is wider than
+  /// `width` pixels when drawn with this font. Lines are broken
+  /// between words where possible, and within words that are
+  /// too long to fit on a line of their own.
+  pub fn wrap(&self, text: &str, width: usize) -> String {
+    let columns = ((width + 1) / self.advance()).max(1);
+    let mut wrapped = String::new();
+
+    for (i, line) in text.split('\n').enumerate() {
+      if i > 0 {
+        wrapped.push('\n');
+      }
+
+      // Leading spaces are kept as indentation on the first segment
+      // of the line
+      let words = line.trim_start_matches(' ');
+      let indent = line.len() - words.len();
+      let mut line_length = 0;
+
+      for _ in 0..indent {
+        if line_length == columns {
+          wrapped.push('\n');
+          line_length = 0;
+        }
+        wrapped.push(' ');
+        line_length += 1;
+      }
+
+      for (j, word) in words.split(' ').enumerate() {
+        let word_length = word.chars().count();
+
+        if j > 0 && line_length > 0 {
+          if line_length + 1 + word_length <= columns {
+            wrapped.push(' ');
+            line_length += 1;
+          } else {
+            wrapped.push('\n');
+            line_length = 0;
+          }
+        }
+
+        for character in word.chars() {
+          if line_length == columns {
+            wrapped.push('\n');
+            line_length = 0;
+          }
+          wrapped.push(character);
+          line_length += 1;
+        }
+      }
+    }
+
+    wrapped
+  }
+}
+
+impl<'glyphs> Default for Font<'glyphs> {
+  fn default() -> Font<'glyphs> {
+    Font::builtin()
+  }
+}
+
+impl<'pixels> Canvas<'pixels> {
+  /// Draw `text` with the built-in font, with the upper left corner
+  /// of the first glyph at `(x, y)`. Newlines start a new line.
+  pub fn draw_text(&mut self, x: isize, y: isize, text: &str, color: Pixel) {
+    self.draw_text_with(&Font::builtin(), x, y, text, color);
+  }
+
+  /// Draw `text` with `font`, with the upper left corner of the
+  /// first glyph at `(x, y)`. Newlines start a new line.
+  pub fn draw_text_with(&mut self, font: &Font, x: isize, y: isize, text: &str, color: Pixel) {
+    for (row, line) in text.split('\n').enumerate() {
+      let top = y + (row * font.line_height()) as isize;
+      for (column, character) in line.chars().enumerate() {
+        let left = x + (column * font.advance()) as isize;
+        for glyph_y in 0..font.glyph_height {
+          for glyph_x in 0..font.glyph_width {
+            if font.is_set(character, glyph_x, glyph_y) {
+              self.set(left + glyph_x as isize, top + glyph_y as isize, color);
+            }
+          }
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use {rgb, rgba};
+
+  #[test]
+  fn draw_text() {
+    let black = rgb(0.0, 0.0, 0.0);
+    let white = rgb(1.0, 1.0, 1.0);
+    let mut pixels = vec![black; 12 * 8];
+    Canvas::new(&mut pixels, (12, 8)).draw_text(1, 1, "Hi", white);
+
+    let rows = pixels
+      .chunks(12)
+      .map(|row| {
+        row
+          .iter()
+          .map(|pixel| if *pixel == white { '#' } else { '.' })
+          .collect::<String>()
+      })
+      .collect::<Vec<String>>();
+
+    assert_eq!(
+      rows,
+      [
+        "............",
+        ".#...#...#..",
+        ".#...#......",
+        ".#...#..##..",
+        ".#####...#..",
+        ".#...#...#..",
+        ".#...#...#..",
+        ".#...#..###.",
+      ]
+    );
+  }
+
+  #[test]
+  fn measure() {
+    let font = Font::builtin();
+    assert_eq!(font.measure(""), (0, 7));
+    assert_eq!(font.measure("a"), (5, 7));
+    assert_eq!(font.measure("ab\nc"), (11, 15));
+  }
+
+  #[test]
+  fn wrap() {
+    let font = Font::builtin();
+    // 17 pixels fits three glyphs
+    assert_eq!(font.wrap("ab cd e", 17), "ab\ncd\ne");
+    assert_eq!(font.wrap("a b c\nd", 17), "a b\nc\nd");
+    assert_eq!(font.wrap("abcdefg", 17), "abc\ndef\ng");
+  }
+
+  #[test]
+  fn wrap_indentation() {
+    let font = Font::builtin();
+    // 35 pixels fits six glyphs
+    assert_eq!(font.wrap("  ab cd", 35), "  ab\ncd");
+    assert_eq!(font.wrap("a\n    b c", 35), "a\n    b\nc");
+    assert_eq!(font.wrap(" ab\n   ", 35), " ab\n   ");
+  }
+
+  #[test]
+  fn from_image() {
+    let on = rgba(1.0, 1.0, 1.0, 1.0);
+    let off = rgba(0.0, 0.0, 0.0, 0.0);
+    // Two 1x2 glyphs, for 'a' and 'b', drawn two pixels apart
+    let sheet = [on, off, off, on];
+    let image = Image {
+      width: 2,
+      height: 2,
+      pixels: &sheet,
+    };
+    let font = Font::from_image(&image, (1, 2), 'a');
+
+    let mut pixels = vec![off; 3 * 2];
+    Canvas::new(&mut pixels, (3, 2)).draw_text_with(&font, 0, 0, "abc", on);
+    assert_eq!(pixels, [on, off, off, off, off, on]);
+  }
+}
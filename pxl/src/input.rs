@@ -0,0 +1,294 @@
+//! Input state tracking
+
+use std::{collections::HashSet, time::Duration};
+
+use {Button, ButtonState, Event, KeyCode};
+
+/// The state of a program's input, updated by the runtime before
+/// every call to `Program::update`.
+///
+/// In addition to the events that occurred since the last tick, an
+/// `Input` tracks which buttons and keys are currently held, and
+/// which were pressed or released since the last tick.
+///
+/// A button that was both pressed and released since the last tick,
+/// for example because it was tapped quickly, will be reported by
+/// both `was_pressed` and `was_released`, but not by `is_held`.
+#[derive(Clone, Debug, Default)]
+pub struct Input {
+  events: Vec<Event>,
+  held: HashSet<(usize, Button)>,
+  pressed: HashSet<(usize, Button)>,
+  released: HashSet<(usize, Button)>,
+  held_keys: HashSet<KeyCode>,
+  pressed_keys: HashSet<KeyCode>,
+  released_keys: HashSet<KeyCode>,
+  text: String,
+  pointer: Option<(f32, f32)>,
+  frame_time: Duration,
+}
+
+impl Input {
+  /// Create a new `Input` with no buttons or keys held
+  pub fn new() -> Input {
+    Input::default()
+  }
+
+  /// Start a new tick, updating input state with `events`,
+  /// the events that occurred since the last tick.
+  ///
+  /// This is called by the runtime, but may also be called
+  /// directly, for example when testing.
+  pub fn update(&mut self, events: &[Event]) {
+    self.events.clear();
+    self.events.extend_from_slice(events);
+    self.pressed.clear();
+    self.released.clear();
+    self.pressed_keys.clear();
+    self.released_keys.clear();
+    self.text.clear();
+
+    for event in events {
+      match *event {
+        Event::Button {
+          button,
+          state: ButtonState::Pressed,
+          player,
+        } => {
+          // Held keys repeat, producing repeated presses of the buttons
+          // they're bound to, which should not count as new presses
+          if self.held.insert((player, button)) {
+            self.pressed.insert((player, button));
+          }
+        }
+        Event::Button {
+          button,
+          state: ButtonState::Released,
+          player,
+        } => {
+          self.held.remove(&(player, button));
+          self.released.insert((player, button));
+        }
+        Event::KeyDown { key, .. } => {
+          // Keys that are held down generate repeated `KeyDown`
+          // events, which should not count as new presses
+          if self.held_keys.insert(key) {
+            self.pressed_keys.insert(key);
+          }
+        }
+        Event::KeyUp { key, .. } => {
+          self.held_keys.remove(&key);
+          self.released_keys.insert(key);
+        }
+        Event::Key { character } => self.text.push(character),
+        Event::PointerMoved { x, y } | Event::PointerButton { x, y, .. } => {
+          self.pointer = Some((x, y))
+        }
+        Event::Scroll { .. } => {}
+      }
+    }
+  }
+
+  /// Set the duration of the most recent frame
+  ///
+  /// This is called by the runtime, but may also be called
+  /// directly, for example when testing.
+  pub fn set_frame_time(&mut self, frame_time: Duration) {
+    self.frame_time = frame_time;
+  }
+
+  /// The events that occurred since the last tick
+  pub fn events(&self) -> &[Event] {
+    &self.events
+  }
+
+  /// Returns true if `button` is currently held down by any player
+  pub fn is_held(&self, button: Button) -> bool {
+    self.held.iter().any(|&(_, held)| held == button)
+  }
+
+  /// Returns true if `button` was pressed by any player
+  /// since the last tick
+  pub fn was_pressed(&self, button: Button) -> bool {
+    self.pressed.iter().any(|&(_, pressed)| pressed == button)
+  }
+
+  /// Returns true if `button` was released by any player
+  /// since the last tick
+  pub fn was_released(&self, button: Button) -> bool {
+    self
+      .released
+      .iter()
+      .any(|&(_, released)| released == button)
+  }
+
+  /// Returns true if `player`'s `button` is currently held down
+  pub fn is_held_by(&self, player: usize, button: Button) -> bool {
+    self.held.contains(&(player, button))
+  }
+
+  /// Returns true if `player`'s `button` was pressed since the last tick
+  pub fn was_pressed_by(&self, player: usize, button: Button) -> bool {
+    self.pressed.contains(&(player, button))
+  }
+
+  /// Returns true if `player`'s `button` was released since the last tick
+  pub fn was_released_by(&self, player: usize, button: Button) -> bool {
+    self.released.contains(&(player, button))
+  }
+
+  /// Returns true if `key` is currently held down
+  pub fn is_key_held(&self, key: KeyCode) -> bool {
+    self.held_keys.contains(&key)
+  }
+
+  /// Returns true if `key` was pressed since the last tick
+  pub fn was_key_pressed(&self, key: KeyCode) -> bool {
+    self.pressed_keys.contains(&key)
+  }
+
+  /// Returns true if `key` was released since the last tick
+  pub fn was_key_released(&self, key: KeyCode) -> bool {
+    self.released_keys.contains(&key)
+  }
+
+  /// The text typed since the last tick
+  pub fn text(&self) -> &str {
+    &self.text
+  }
+
+  /// The wall-clock duration of the most recent frame, including any
+  /// time spent waiting for vsync or the frame rate limiter. Unlike
+  /// the `elapsed` passed to `Program::update`, this is not affected
+  /// by the timestep, so it is suitable for displaying the frame rate.
+  pub fn frame_time(&self) -> Duration {
+    self.frame_time
+  }
+
+  /// The most recent position of the pointer, in pixel coordinates,
+  /// or `None` if the pointer has not yet been seen
+  pub fn pointer(&self) -> Option<(f32, f32)> {
+    self.pointer
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use Modifiers;
+
+  fn button(button: Button, state: ButtonState) -> Event {
+    Event::Button {
+      button,
+      state,
+      player: 0,
+    }
+  }
+
+  #[test]
+  fn buttons() {
+    let mut input = Input::new();
+
+    input.update(&[button(Button::Left, ButtonState::Pressed)]);
+    assert!(input.is_held(Button::Left));
+    assert!(input.was_pressed(Button::Left));
+    assert!(!input.was_released(Button::Left));
+
+    input.update(&[]);
+    assert!(input.is_held(Button::Left));
+    assert!(!input.was_pressed(Button::Left));
+
+    input.update(&[
+      button(Button::Left, ButtonState::Released),
+      button(Button::Action, ButtonState::Pressed),
+      button(Button::Action, ButtonState::Released),
+    ]);
+    assert!(!input.is_held(Button::Left));
+    assert!(input.was_released(Button::Left));
+    assert!(!input.is_held(Button::Action));
+    assert!(input.was_pressed(Button::Action));
+    assert!(input.was_released(Button::Action));
+  }
+
+  #[test]
+  fn repeated_presses() {
+    let mut input = Input::new();
+
+    input.update(&[button(Button::Up, ButtonState::Pressed)]);
+    assert!(input.was_pressed(Button::Up));
+
+    input.update(&[button(Button::Up, ButtonState::Pressed)]);
+    assert!(input.is_held(Button::Up));
+    assert!(!input.was_pressed(Button::Up));
+  }
+
+  #[test]
+  fn players() {
+    let mut input = Input::new();
+
+    input.update(&[Event::Button {
+      button: Button::Start,
+      state: ButtonState::Pressed,
+      player: 1,
+    }]);
+    assert!(input.is_held(Button::Start));
+    assert!(input.is_held_by(1, Button::Start));
+    assert!(!input.is_held_by(0, Button::Start));
+    assert!(input.was_pressed_by(1, Button::Start));
+    assert!(!input.was_pressed_by(0, Button::Start));
+
+    input.update(&[Event::Button {
+      button: Button::Start,
+      state: ButtonState::Released,
+      player: 1,
+    }]);
+    assert!(!input.is_held(Button::Start));
+    assert!(input.was_released_by(1, Button::Start));
+    assert!(!input.was_released_by(0, Button::Start));
+  }
+
+  #[test]
+  fn keys() {
+    let mut input = Input::new();
+    let modifiers = Modifiers::default();
+    let down = Event::KeyDown {
+      key: KeyCode::W,
+      modifiers,
+    };
+
+    input.update(&[down]);
+    assert!(input.is_key_held(KeyCode::W));
+    assert!(input.was_key_pressed(KeyCode::W));
+
+    input.update(&[down]);
+    assert!(input.is_key_held(KeyCode::W));
+    assert!(!input.was_key_pressed(KeyCode::W));
+
+    input.update(&[Event::KeyUp {
+      key: KeyCode::W,
+      modifiers,
+    }]);
+    assert!(!input.is_key_held(KeyCode::W));
+    assert!(input.was_key_released(KeyCode::W));
+  }
+
+  #[test]
+  fn text_and_pointer() {
+    let mut input = Input::new();
+    assert_eq!(input.pointer(), None);
+
+    input.update(&[
+      Event::Key { character: 'h' },
+      Event::PointerMoved { x: 1.0, y: 2.0 },
+      Event::Key { character: 'i' },
+    ]);
+    assert_eq!(input.text(), "hi");
+    assert_eq!(input.pointer(), Some((1.0, 2.0)));
+    assert_eq!(input.events().len(), 3);
+
+    input.update(&[]);
+    assert_eq!(input.text(), "");
+    assert_eq!(input.pointer(), Some((1.0, 2.0)));
+  }
+}
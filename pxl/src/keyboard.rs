@@ -0,0 +1,179 @@
+//! Keyboard key codes and modifiers
+
+macro_rules! key_codes {
+  ($($(#[$attribute:meta])* $variant:ident => $name:expr,)*) => {
+    /// Enum representing physical keyboard keys
+    ///
+    /// Key codes identify keys by the character they produce in
+    /// the US layout, regardless of the active keyboard layout or
+    /// the modifiers that are held, so `KeyCode::A` is produced
+    /// by the A key whether or not shift is held. Use `Event::Key`
+    /// for text input.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+    pub enum KeyCode {
+      $($(#[$attribute])* $variant,)*
+    }
+
+    impl KeyCode {
+      /// All key codes
+      pub const ALL: &'static [KeyCode] = &[$(KeyCode::$variant,)*];
+
+      /// The key's name, as used in recordings and bindings
+      pub fn name(self) -> &'static str {
+        match self {
+          $(KeyCode::$variant => $name,)*
+        }
+      }
+
+      /// Look up a key by its name, ignoring case
+      pub fn from_name(name: &str) -> Option<KeyCode> {
+        KeyCode::ALL
+          .iter()
+          .cloned()
+          .find(|key| key.name().eq_ignore_ascii_case(name))
+      }
+    }
+  }
+}
+
+key_codes! {
+  /// The A key
+  A => "a",
+  /// The B key
+  B => "b",
+  /// The C key
+  C => "c",
+  /// The D key
+  D => "d",
+  /// The E key
+  E => "e",
+  /// The F key
+  F => "f",
+  /// The G key
+  G => "g",
+  /// The H key
+  H => "h",
+  /// The I key
+  I => "i",
+  /// The J key
+  J => "j",
+  /// The K key
+  K => "k",
+  /// The L key
+  L => "l",
+  /// The M key
+  M => "m",
+  /// The N key
+  N => "n",
+  /// The O key
+  O => "o",
+  /// The P key
+  P => "p",
+  /// The Q key
+  Q => "q",
+  /// The R key
+  R => "r",
+  /// The S key
+  S => "s",
+  /// The T key
+  T => "t",
+  /// The U key
+  U => "u",
+  /// The V key
+  V => "v",
+  /// The W key
+  W => "w",
+  /// The X key
+  X => "x",
+  /// The Y key
+  Y => "y",
+  /// The Z key
+  Z => "z",
+  /// The 0 key in the number row
+  Key0 => "0",
+  /// The 1 key in the number row
+  Key1 => "1",
+  /// The 2 key in the number row
+  Key2 => "2",
+  /// The 3 key in the number row
+  Key3 => "3",
+  /// The 4 key in the number row
+  Key4 => "4",
+  /// The 5 key in the number row
+  Key5 => "5",
+  /// The 6 key in the number row
+  Key6 => "6",
+  /// The 7 key in the number row
+  Key7 => "7",
+  /// The 8 key in the number row
+  Key8 => "8",
+  /// The 9 key in the number row
+  Key9 => "9",
+  /// The F1 function key
+  F1 => "f1",
+  /// The F2 function key
+  F2 => "f2",
+  /// The F3 function key
+  F3 => "f3",
+  /// The F4 function key
+  F4 => "f4",
+  /// The F5 function key
+  F5 => "f5",
+  /// The F6 function key
+  F6 => "f6",
+  /// The F7 function key
+  F7 => "f7",
+  /// The F8 function key
+  F8 => "f8",
+  /// The F9 function key
+  F9 => "f9",
+  /// The F10 function key
+  F10 => "f10",
+  /// The F11 function key
+  F11 => "f11",
+  /// The F12 function key
+  F12 => "f12",
+  /// The escape key
+  Escape => "escape",
+  /// The enter, or return, key
+  Enter => "enter",
+  /// The tab key
+  Tab => "tab",
+  /// The backspace key
+  Backspace => "backspace",
+  /// The spacebar
+  Space => "space",
+  /// The up arrow key
+  Up => "up",
+  /// The down arrow key
+  Down => "down",
+  /// The left arrow key
+  Left => "left",
+  /// The right arrow key
+  Right => "right",
+  /// The insert key
+  Insert => "insert",
+  /// The delete key
+  Delete => "delete",
+  /// The home key
+  Home => "home",
+  /// The end key
+  End => "end",
+  /// The page up key
+  PageUp => "page-up",
+  /// The page down key
+  PageDown => "page-down",
+}
+
+/// The modifier keys that were held when a key event occurred
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub struct Modifiers {
+  /// Either shift key
+  pub shift: bool,
+  /// Either control key
+  pub ctrl: bool,
+  /// Either alt, or option, key
+  pub alt: bool,
+  /// Either logo key, i.e. the Windows or command key
+  pub logo: bool,
+}
@@ -9,20 +9,52 @@
 //! ## Features
 //!
 //! - Pixel-based rendering
-//! - Sample-based audio synthesis
+//! - Sample-based audio synthesis, with oscillators, envelopes, filters, and a mixer
 //! - Custom vertex and fragment shaders
 //! - `pxl-build`, a compile-time resource loading crate
-//! - Action and text input
+//! - Action, text, and pointer input
+//! - Drawing primitives, image blitting, bitmap text, and color math
+//! - Screenshots, GIF capture, and offline rendering of video and audio
 
+mod audio;
+mod bindings;
+mod blit;
+mod canvas;
+mod color;
+mod dsp;
+mod font;
+mod input;
+mod keyboard;
+mod mixer;
+mod recording;
 mod runtime;
 
+pub use audio::{
+  render_audio, render_audio_with_buffer_size, write_wav, WavFormat, DEFAULT_BUFFER_SIZE,
+};
+pub use bindings::{Binding, Bindings};
+pub use blit::{Blend, Blit, Rotation};
+pub use canvas::Canvas;
+pub use color::{hsl, hsv};
+pub use dsp::{
+  Biquad, Delay, Envelope, FilterKind, Gain, Generator, Noise, Oscillator, Pan, Processor, Waveform,
+};
+pub use font::Font;
+pub use input::Input;
+pub use keyboard::{KeyCode, Modifiers};
+pub use mixer::{Mixer, MixerHandle, Playback, VoiceId, MAX_QUEUED_COMMANDS, MAX_VOICES};
+pub use recording::{Recording, Tick};
+pub use runtime::Error;
+
 pub use std::{
-  sync::{Arc, Mutex}, time::Duration,
+  path::Path, sync::{Arc, Mutex}, time::Duration,
 };
 
 /// The number of audio samples in a second. Synthesizer
 /// implementations will need this to calculate the current
-/// time from the number of samples played so far.
+/// time from the number of samples played so far. Synthesizers
+/// always run at this rate, and their output is resampled if the
+/// audio output device uses a different one.
 pub const SAMPLES_PER_SECOND: u32 = 48_000;
 
 /// An RGBA pixel. Components should normally be between
@@ -36,10 +68,10 @@ pub struct Pixel {
   pub green: f32,
   /// The blue component
   pub blue: f32,
-  /// The alpha component. Currently ignored, but in the
-  /// future, pixels rendered with an alpha value of less
-  /// than 1.0 may be transparent, exposing the desktop or
-  /// window behind the `pxl` window.
+  /// The alpha component. If the program's window is transparent,
+  /// pixels displayed with an alpha value of less than 1.0 are
+  /// transparent, exposing the desktop or window behind the `pxl`
+  /// window. Otherwise ignored.
   pub alpha: f32,
 }
 
@@ -61,6 +93,7 @@ pub fn rgb(red: f32, green: f32, blue: f32) -> Pixel {
 
 /// An image made of pixels. Used by the `pxl-build` crate
 /// for image resources
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Image<'pixels> {
   /// Width in pixels of the image
   pub width: usize,
@@ -83,11 +116,13 @@ pub struct AudioSample {
 
 /// Enum representing input buttons
 ///
-/// In the current runtime, the arrow keys produce `Left`, `Right`, `Up` and
-/// `Down` events, and the spacebar produces `Action` events.
+/// Buttons are abstract, and are produced by keys according to
+/// the program's `Bindings`. By default, the arrow keys produce
+/// `Left`, `Right`, `Up` and `Down` events, and the spacebar
+/// produces `Action` events.
 ///
-/// Buttons are intended to be abstract. In the future gamepad,
-/// touch, and/or keyboard input may produce Button events
+/// Buttons are intended to be abstract. In the future gamepad
+/// and touch input may also produce Button events
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Button {
   /// Left button
@@ -100,6 +135,56 @@ pub enum Button {
   Down,
   /// Action button
   Action,
+  /// Secondary action button
+  Action2,
+  /// Start button
+  Start,
+  /// Select button
+  Select,
+  /// Left shoulder button
+  LeftShoulder,
+  /// Right shoulder button
+  RightShoulder,
+}
+
+impl Button {
+  /// All buttons
+  pub const ALL: &'static [Button] = &[
+    Button::Left,
+    Button::Right,
+    Button::Up,
+    Button::Down,
+    Button::Action,
+    Button::Action2,
+    Button::Start,
+    Button::Select,
+    Button::LeftShoulder,
+    Button::RightShoulder,
+  ];
+
+  /// The button's name, as used in recordings and bindings
+  pub fn name(self) -> &'static str {
+    match self {
+      Button::Left => "left",
+      Button::Right => "right",
+      Button::Up => "up",
+      Button::Down => "down",
+      Button::Action => "action",
+      Button::Action2 => "action2",
+      Button::Start => "start",
+      Button::Select => "select",
+      Button::LeftShoulder => "left-shoulder",
+      Button::RightShoulder => "right-shoulder",
+    }
+  }
+
+  /// Look up a button by its name, ignoring case
+  pub fn from_name(name: &str) -> Option<Button> {
+    Button::ALL
+      .iter()
+      .cloned()
+      .find(|button| button.name().eq_ignore_ascii_case(name))
+  }
 }
 
 /// Enum representing the state of an input button
@@ -111,12 +196,34 @@ pub enum ButtonState {
   Released,
 }
 
+/// Enum representing pointer buttons, e.g. mouse buttons
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PointerButton {
+  /// The left, or primary, button
+  Left,
+  /// The right, or secondary, button
+  Right,
+  /// The middle button, often a clickable scroll wheel
+  Middle,
+  /// Any other button
+  Other(u8),
+}
+
 /// Input events
 ///
-/// Note that a single keyboard press may generate both
-/// a `Button` and `Key` event. For example, the spacebar
-/// will generate both a `Button::Action` event and a
+/// Note that a single keyboard press may generate `Button`,
+/// `KeyDown` and `Key` events. For example, the spacebar
+/// will generate a `Button::Action` event, a
+/// `KeyDown{key: KeyCode::Space, ..}` event, and a
 /// `Key{character: ' '}` event.
+///
+/// Pointer positions are given in the coordinates of the pixel
+/// slice passed to `render()`, taking into account how the pixels
+/// are scaled to fit the window, so the pixel under the pointer is
+/// `pixels[x as usize + y as usize * WIDTH]`. Positions outside of
+/// the pixel surface, for example when the pointer has left the
+/// window, will be less than zero or greater than or equal to the
+/// width or height of the surface.
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Event {
   /// A button event, representing a pressed or released button
@@ -125,12 +232,198 @@ pub enum Event {
     button: Button,
     /// The state of the button: `Pressed` or `Released`
     state: ButtonState,
+    /// The index of the player the button belongs to,
+    /// starting from zero
+    player: usize,
   },
   /// A text input event
   Key {
     /// The input character
     character: char,
   },
+  /// A key was pressed. Keys that are held down may generate
+  /// repeated `KeyDown` events.
+  KeyDown {
+    /// The key in question
+    key: KeyCode,
+    /// The modifier keys that were held
+    modifiers: Modifiers,
+  },
+  /// A key was released
+  KeyUp {
+    /// The key in question
+    key: KeyCode,
+    /// The modifier keys that were held
+    modifiers: Modifiers,
+  },
+  /// The pointer moved
+  PointerMoved {
+    /// The horizontal position of the pointer
+    x: f32,
+    /// The vertical position of the pointer
+    y: f32,
+  },
+  /// A pointer button was pressed or released
+  PointerButton {
+    /// The pointer button in question
+    button: PointerButton,
+    /// The state of the button: `Pressed` or `Released`
+    state: ButtonState,
+    /// The horizontal position of the pointer
+    x: f32,
+    /// The vertical position of the pointer
+    y: f32,
+  },
+  /// The scroll wheel or touchpad was scrolled
+  Scroll {
+    /// Horizontal scroll distance in lines. Positive values
+    /// indicate scrolling to the right.
+    x: f32,
+    /// Vertical scroll distance in lines. Positive values
+    /// indicate scrolling up.
+    y: f32,
+  },
+}
+
+/// The output of a single frame of a program run with `run_headless`
+/// or `run_headless_with_window_size`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+  /// The program's title when the frame was rendered
+  pub title: String,
+  /// The program's resolution when the frame was rendered
+  pub resolution: (usize, usize),
+  /// The rendered pixels, containing `resolution.0 * resolution.1`
+  /// pixels, in the same order as the slice passed to `render()`
+  pub pixels: Vec<Pixel>,
+  /// Audio samples produced by the program's synthesizer during
+  /// the frame. Empty if the program has no synthesizer.
+  pub samples: Vec<AudioSample>,
+}
+
+/// How the program's pixels are scaled to fill the window, returned
+/// by `Program::scaling`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Scaling {
+  /// Stretch the pixels across the whole window, distorting
+  /// the aspect ratio if the window's differs from the program's
+  Stretch,
+  /// Scale the pixels as large as possible while preserving the
+  /// aspect ratio, filling the rest of the window with black bars
+  Fit,
+  /// Like `Fit`, but only scale by whole numbers, so every pixel
+  /// is the same size on screen
+  Integer,
+}
+
+/// The size of a screenshot, returned by `Program::screenshot`
+///
+/// Screenshots capture the final displayed frame, after the
+/// program's shaders and filters have run. They are saved in the
+/// current directory as PNG files named after the time at which
+/// they were taken.
+///
+/// Pressing F12 takes a screenshot at window resolution, and
+/// Shift+F12 takes one at program resolution. Ctrl+F12 starts and
+/// stops capturing an animated GIF, see `Program::toggle_gif_capture`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Screenshot {
+  /// One pixel for each of the program's pixels
+  ProgramResolution,
+  /// One pixel for each of the window's physical pixels, including
+  /// any letterbox bars
+  WindowResolution,
+}
+
+/// Whether a window is fullscreen
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Fullscreen {
+  /// A normal window
+  Windowed,
+  /// Exclusive fullscreen on the current monitor
+  Fullscreen,
+  /// A window without decorations covering the whole of
+  /// the current monitor
+  Borderless,
+}
+
+/// Configuration of the program's window, returned by
+/// `Program::window_config`
+///
+/// Pressing Alt+Enter toggles between a normal window and
+/// `fullscreen`, or exclusive fullscreen if `fullscreen`
+/// is `Windowed`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WindowConfig {
+  /// Whether the window is initially fullscreen
+  pub fullscreen: Fullscreen,
+  /// The initial size of the window as a multiple of the program's
+  /// resolution, or `None` to make the window as large as will
+  /// comfortably fit on the monitor
+  pub scale: Option<f64>,
+  /// The minimum size of the window, in logical pixels
+  pub min_size: Option<(u32, u32)>,
+  /// The maximum size of the window, in logical pixels
+  pub max_size: Option<(u32, u32)>,
+  /// Whether the window may be resized by the user
+  pub resizable: bool,
+  /// Whether the window has a title bar and border
+  pub decorations: bool,
+  /// The window's icon, as shown in the title bar and taskbar
+  pub icon: Option<&'static Image<'static>>,
+  /// Whether the window is transparent where the displayed pixels,
+  /// after shaders and filters have run, have an alpha value of less
+  /// than 1.0. Letterbox bars are fully transparent. Useful for
+  /// desktop widgets and overlays, in which case `decorations` should
+  /// usually be false. Requires a compositing window manager.
+  pub transparent: bool,
+}
+
+impl Default for WindowConfig {
+  fn default() -> WindowConfig {
+    WindowConfig {
+      fullscreen: Fullscreen::Windowed,
+      scale: None,
+      min_size: None,
+      max_size: None,
+      resizable: true,
+      decorations: true,
+      icon: None,
+      transparent: false,
+    }
+  }
+}
+
+/// How the runtime paces the frames it presents, returned by
+/// `Program::frame_pacing`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FramePacing {
+  /// Wait for the display's vertical blank before presenting each
+  /// frame, limiting the frame rate to the display's refresh rate
+  /// and avoiding tearing
+  pub vsync: bool,
+  /// If not `None`, sleep at the end of each frame so that no more
+  /// than this many frames are presented per second
+  pub target_frame_rate: Option<u32>,
+}
+
+impl FramePacing {
+  /// The minimum duration of a frame, if the frame rate is limited
+  pub fn frame_duration(&self) -> Option<Duration> {
+    match self.target_frame_rate {
+      Some(0) | None => None,
+      Some(frame_rate) => Some(Duration::new(0, 1_000_000_000 / frame_rate)),
+    }
+  }
+}
+
+impl Default for FramePacing {
+  fn default() -> FramePacing {
+    FramePacing {
+      vsync: true,
+      target_frame_rate: None,
+    }
+  }
 }
 
 /// Trait for things that can generate sound
@@ -183,6 +476,15 @@ pub trait Program: 'static {
   /// elements.
   fn resolution(&self) -> (usize, usize);
 
+  /// Return how the program's pixels should be scaled to the window
+  ///
+  /// Will be called immediately before calling `render()`. Also used
+  /// when choosing the initial size of the window, and when converting
+  /// pointer positions to pixel coordinates.
+  fn scaling(&self) -> Scaling {
+    Scaling::Stretch
+  }
+
   /// Return the vertex shader to be used in the runtime's
   /// rendering pipeline
   ///
@@ -232,12 +534,95 @@ pub trait Program: 'static {
     false
   }
 
+  /// Return `Some` to take a screenshot of the current frame
+  ///
+  /// Called by the runtime after every frame is rendered. Screenshots
+  /// are only taken by the windowed runtime.
+  fn screenshot(&mut self) -> Option<Screenshot> {
+    None
+  }
+
+  /// Return true to start capturing an animated GIF of the displayed
+  /// frames, or to stop capturing and save the GIF
+  ///
+  /// Called by the runtime after every frame is rendered. Pressing
+  /// Ctrl+F12 also starts and stops capturing. Frames are captured at
+  /// program resolution, and are reduced to 256 colors. GIFs are saved
+  /// in the current directory, and are only captured by the windowed
+  /// runtime.
+  fn toggle_gif_capture(&mut self) -> bool {
+    false
+  }
+
+  /// Return the maximum duration of a captured GIF
+  ///
+  /// Called by the runtime when GIF capture starts. Capture stops and
+  /// the GIF is saved when this duration is reached, or if the
+  /// program's resolution changes.
+  fn max_gif_duration(&self) -> Duration {
+    Duration::from_secs(30)
+  }
+
   /// Process events and update the state of the program.
   ///
+  /// Called by the default implementation of `update`
+  ///
   /// * `elapsed` — time elapsed since the last call to tick `tick`
   /// * `events` — events that have occurred since the last call to `tick`
   fn tick(&mut self, _elapsed: Duration, _events: &[Event]) {}
 
+  /// Return the configuration of the program's window
+  ///
+  /// Called by the runtime during initialization
+  fn window_config(&self) -> WindowConfig {
+    WindowConfig::default()
+  }
+
+  /// Return how the runtime should pace frames
+  ///
+  /// Called by the runtime during initialization. By default, vsync
+  /// is enabled and the frame rate is not otherwise limited. On some
+  /// systems vsync is unreliable, so programs may wish to disable it
+  /// and set a target frame rate instead. The duration of each frame
+  /// is available from `Input::frame_time`.
+  fn frame_pacing(&self) -> FramePacing {
+    FramePacing::default()
+  }
+
+  /// Return the duration of each tick when using a fixed timestep,
+  /// or `None` to tick once per frame
+  ///
+  /// Called by the runtime during initialization. By default, `update`
+  /// is called once per frame with the wall-clock time elapsed since
+  /// the previous frame, so simulations may behave differently on
+  /// displays with different refresh rates. If this returns a duration,
+  /// `update` is instead called zero or more times per frame, always
+  /// with exactly that duration, and `render_interpolated` is given
+  /// the fraction of a tick that has elapsed since the most recent one.
+  ///
+  /// If a frame takes so long that more than a handful of ticks are
+  /// due, the excess time is dropped, so that a slow program slows
+  /// down instead of falling further and further behind.
+  fn tick_rate(&self) -> Option<Duration> {
+    None
+  }
+
+  /// Process input and update the state of the program.
+  ///
+  /// Called by the runtime once per pass through the event loop, or
+  /// once per tick if `tick_rate` returns a duration. The default
+  /// implementation calls `tick` with the events from `input`,
+  /// so programs may implement whichever of `update` and `tick` is
+  /// most convenient.
+  ///
+  /// * `elapsed` — time elapsed since the last call to `update`
+  /// * `input` — the current state of the program's input, including
+  ///             the events that have occurred since the last call
+  ///             to `update`
+  fn update(&mut self, elapsed: Duration, input: &Input) {
+    self.tick(elapsed, input.events());
+  }
+
   /// Draw to the display
   ///
   /// Called by the runtime whenever the display is ready to present a new frame
@@ -251,6 +636,59 @@ pub trait Program: 'static {
   ///              corner of the screen
   fn render(&mut self, _pixels: &mut [Pixel]) {}
 
+  /// Return the palette for indexed rendering, or `None` to render
+  /// RGBA pixels
+  ///
+  /// Called by the runtime immediately before rendering. If this
+  /// returns a palette, `render_indexed` is called instead of `render`,
+  /// and each index it writes is replaced by the color at that position
+  /// in the palette. Palettes may contain up to 256 colors, and indices
+  /// past the end of the palette are drawn black.
+  ///
+  /// Only the indices and the palette are sent to the graphics card,
+  /// and colors are looked up by the runtime before the program's
+  /// fragment shader runs, so changing the palette, for example to
+  /// cycle colors, is free.
+  fn palette(&self) -> Option<&[Pixel]> {
+    None
+  }
+
+  /// Draw to the display using palette indices
+  ///
+  /// Called by the runtime instead of `render` if `palette` returns
+  /// a palette
+  ///
+  /// * `indices` — a slice of palette indices with `WIDTH * HEIGHT`
+  ///               elements, in the same order as the pixels passed
+  ///               to `render`
+  fn render_indexed(&mut self, _indices: &mut [u8]) {}
+
+  /// Draw to the display, interpolating between the previous and
+  /// current state of the program
+  ///
+  /// Called by the runtime instead of `render`. The default
+  /// implementation ignores `alpha` and calls `render`.
+  ///
+  /// * `pixels` — as for `render`
+  /// * `alpha` — the fraction of a tick, between 0 and 1, that has
+  ///             elapsed since the most recent call to `update`.
+  ///             Programs using a fixed timestep may draw the state
+  ///             `previous * (1 - alpha) + current * alpha` to
+  ///             animate smoothly at any frame rate. Always 1 when
+  ///             not using a fixed timestep.
+  fn render_interpolated(&mut self, pixels: &mut [Pixel], _alpha: f64) {
+    self.render(pixels);
+  }
+
+  /// Return the program's key bindings
+  ///
+  /// Will be called by the runtime during initialization. Determines
+  /// which keys produce which `Button` events. To let players choose
+  /// their own bindings, load them from a file with `Bindings::load`.
+  fn bindings(&self) -> Bindings {
+    Bindings::default()
+  }
+
   /// The program's synthesizer
   ///
   /// Will be called by the runtime during initialization. If it returns
@@ -319,7 +757,12 @@ pub fn run<P: Program>() -> ! {
   let result = runtime::Runtime::new(Box::new(program));
 
   // Run the program
-  if let Err(error) = result.and_then(|runtime| runtime.run()) {
+  exit(result.and_then(|runtime| runtime.run()))
+}
+
+/// Exit the process after a runtime has finished running
+fn exit(result: Result<(), Error>) -> ! {
+  if let Err(error) = result {
     // Print an error message if something went wrong
     eprintln!("{}", error);
     // Terminate with an status code indicating that something went wrong
@@ -329,3 +772,184 @@ pub fn run<P: Program>() -> ! {
     std::process::exit(0);
   }
 }
+
+/// Run a `pxl::Program`, recording every call to `Program::tick`.
+///
+/// Each tick is saved to the file at `path` before it is run, so the
+/// recording is complete even if the program exits with an error or
+/// panics. It can be replayed with `replay` or `replay_headless`. See
+/// `Recording` for details of the format.
+pub fn record<P: Program>(path: impl AsRef<Path>) -> ! {
+  let program = P::new();
+  let result = runtime::Runtime::new(Box::new(program));
+
+  exit(result.and_then(|mut runtime| {
+    runtime.record(path.as_ref().to_path_buf())?;
+    runtime.run()
+  }))
+}
+
+/// Run a `pxl::Program`, replaying the recording in the file at `path`.
+///
+/// Instead of the events and elapsed time of the current run, calls to
+/// `Program::tick` receive exactly the events and elapsed time that
+/// were recorded. Keyboard input is ignored, and the program exits
+/// when the recording runs out.
+pub fn replay<P: Program>(path: impl AsRef<Path>) -> ! {
+  let program = P::new();
+
+  exit(Recording::load(path).and_then(|recording| {
+    let mut runtime = runtime::Runtime::new(Box::new(program))?;
+    runtime.replay(recording);
+    runtime.run()
+  }))
+}
+
+/// Run a `pxl::Program` without a window, GPU, or audio device,
+/// returning the output of each frame.
+///
+/// The program's methods are called in the same order as they are
+/// by `run`, but time is measured by a virtual clock that advances
+/// by exactly 1/60th of a second every frame, which is divided into
+/// ticks if the program uses a fixed timestep, and the synthesizer, if
+/// any, is asked for as many samples as the virtual clock advanced,
+/// `SAMPLES_PER_SECOND / 60` per frame. No events are delivered to
+/// the program.
+///
+/// The program is run for `frames` frames, or until `should_quit`
+/// returns true, whichever comes first. Shaders are not compiled
+/// or run, so the returned pixels are exactly those written by `render`.
+///
+/// This is intended for testing:
+///
+/// ```
+/// extern crate pxl;
+///
+/// use pxl::*;
+///
+/// struct Red;
+///
+/// impl Program for Red {
+///   fn new() -> Red {
+///     Red
+///   }
+///
+///   fn resolution(&self) -> (usize, usize) {
+///     (2, 2)
+///   }
+///
+///   fn render(&mut self, pixels: &mut [Pixel]) {
+///     for pixel in pixels {
+///       *pixel = rgb(1.0, 0.0, 0.0);
+///     }
+///   }
+/// }
+///
+/// fn main() {
+///   let frames = run_headless::<Red>(3);
+///   assert_eq!(frames.len(), 3);
+///   assert_eq!(frames[0].pixels, vec![rgb(1.0, 0.0, 0.0); 4]);
+/// }
+/// ```
+pub fn run_headless<P: Program>(frames: usize) -> Vec<Frame> {
+  let program = P::new();
+  runtime::Headless::new(Box::new(program)).run(frames)
+}
+
+/// Run a `pxl::Program` without a window, GPU, or audio device,
+/// presenting each frame to a virtual window of size `window_size`.
+///
+/// This behaves like `run_headless`, except that the returned frames
+/// contain the pixels that would be displayed in a window of size
+/// `window_size`. The default rendering pipeline, which scales the
+/// program's pixels to the size of the window using nearest-neighbor
+/// sampling, is implemented in software, so no GPU is needed.
+///
+/// Returns an error if the program uses a custom vertex, fragment, or
+/// filter shader, since those can only be run by OpenGL.
+pub fn run_headless_with_window_size<P: Program>(
+  frames: usize,
+  window_size: (usize, usize),
+) -> Result<Vec<Frame>, Error> {
+  let program = P::new();
+  let mut headless = runtime::Headless::with_window_size(Box::new(program), window_size)?;
+  Ok(headless.run(frames))
+}
+
+/// Run a `pxl::Program` in the terminal instead of a window.
+///
+/// Pixels are drawn using ANSI truecolor escape codes, with each
+/// character cell displaying two pixels stacked vertically, so a
+/// terminal that supports 24-bit color is required. The program's
+/// pixels are scaled to fill the terminal.
+///
+/// The arrow keys produce `Left`, `Right`, `Up` and `Down` button
+/// events, and the spacebar produces `Action` button events. Since
+/// terminals do not report key releases, buttons are released
+/// immediately after being pressed. All other characters produce
+/// `Key` events. Press Ctrl-C to quit.
+///
+/// Custom shaders are ignored, so what is displayed is the pixels
+/// written by `render`. Audio is played if an audio output device is
+/// available.
+pub fn run_terminal<P: Program>() -> ! {
+  let program = P::new();
+  let result = runtime::Terminal::new(Box::new(program));
+
+  exit(result.and_then(|terminal| terminal.run()))
+}
+
+/// Render `duration` of a `pxl::Program` to files in `directory`,
+/// at `frame_rate` frames per second.
+///
+/// Rendering runs as fast as the program can be rendered, which may
+/// be slower than real time, but the program sees time pass as if it
+/// were running in real time. Every frame, `Program::tick` is called
+/// with a constant `elapsed` of `1 / frame_rate` seconds, divided into
+/// ticks if the program uses a fixed timestep, and the synthesizer, if
+/// any, is asked for exactly the samples that play during the frame,
+/// `SAMPLES_PER_SECOND / frame_rate` of them. Those samples and their
+/// frequencies are passed to the program's shaders, so visualizers
+/// stay in sync with the audio. No events are delivered to the program.
+///
+/// Each frame is written, after shaders and filters have run and at
+/// program resolution, to `frame-000000.png`, `frame-000001.png`, and
+/// so on. If the program has a synthesizer, the audio is written to
+/// `audio.wav`. `directory` is created if it doesn't exist. Rendering
+/// stops early if `should_quit` returns true.
+///
+/// Requires OpenGL, and uses a hidden window. Exits with an error if
+/// `frame_rate` is zero.
+pub fn render_offline<P: Program>(
+  directory: impl AsRef<Path>,
+  frame_rate: u32,
+  duration: Duration,
+) -> ! {
+  let program = P::new();
+  let result = runtime::Offline::new(
+    Box::new(program),
+    directory.as_ref().to_path_buf(),
+    frame_rate,
+    duration,
+  );
+
+  exit(result.and_then(|offline| offline.run()))
+}
+
+/// Run a `pxl::Program` without a window, GPU, or audio device,
+/// replaying `recording`.
+///
+/// This behaves like `run_headless`, except that every call to
+/// `Program::tick` receives the events and elapsed time from the
+/// next tick in `recording`, and the virtual clock advances by that
+/// elapsed time. The program is run until the recording runs out, or
+/// until `should_quit` returns true, whichever comes first.
+///
+/// Combined with `record`, this can be used to turn a bug report
+/// into a regression test.
+pub fn replay_headless<P: Program>(recording: Recording) -> Vec<Frame> {
+  let program = P::new();
+  let mut headless = runtime::Headless::new(Box::new(program));
+  headless.replay(recording);
+  headless.run(usize::max_value())
+}
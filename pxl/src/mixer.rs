@@ -0,0 +1,475 @@
+//! Playing sampled audio clips
+
+use std::sync::{
+  atomic::{AtomicUsize, Ordering}, mpsc::{self, Receiver, SyncSender},
+};
+
+use {Arc, AudioSample, Pan, Synthesizer};
+
+/// The maximum number of voices a mixer plays at once. If a clip is
+/// played when all voices are in use, the oldest voice is stopped.
+pub const MAX_VOICES: usize = 32;
+
+/// The maximum number of commands waiting to be applied by a mixer.
+/// Commands sent when the queue is full are discarded.
+pub const MAX_QUEUED_COMMANDS: usize = 1024;
+
+/// Identifies a voice playing a clip, returned by `MixerHandle::play`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VoiceId(usize);
+
+/// How a voice plays its clip
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Playback {
+  /// Gain applied to the clip, where 1.0 leaves it unchanged
+  pub volume: f32,
+  /// Stereo balance, from -1.0 for only the left channel, through
+  /// 0.0 for both channels unchanged, to 1.0 for only the right
+  /// channel
+  pub pan: f32,
+  /// Playback rate, where 2.0 plays the clip twice as fast and an
+  /// octave higher, and 0.5 half as fast and an octave lower
+  pub pitch: f32,
+  /// Whether the clip restarts from the beginning when it ends,
+  /// instead of stopping
+  pub looping: bool,
+}
+
+impl Default for Playback {
+  fn default() -> Playback {
+    Playback {
+      volume: 1.0,
+      pan: 0.0,
+      pitch: 1.0,
+      looping: false,
+    }
+  }
+}
+
+enum Command {
+  Play {
+    id: VoiceId,
+    clip: &'static [AudioSample],
+    playback: Playback,
+  },
+  Update {
+    id: VoiceId,
+    update: Update,
+  },
+  Stop {
+    id: VoiceId,
+  },
+  StopAll,
+}
+
+enum Update {
+  Volume(f32),
+  Pan(f32),
+  Pitch(f32),
+  Looping(bool),
+}
+
+struct Voice {
+  id: VoiceId,
+  clip: &'static [AudioSample],
+  playback: Playback,
+  position: f64,
+}
+
+impl Voice {
+  /// The sample at the voice's position, interpolating between the
+  /// neighboring samples of the clip
+  fn sample(&self) -> AudioSample {
+    let index = self.position as usize;
+    let fraction = (self.position - index as f64) as f32;
+
+    let current = self.clip[index];
+    let next = match self.clip.get(index + 1) {
+      Some(next) => *next,
+      None if self.playback.looping => self.clip[0],
+      None => current,
+    };
+
+    AudioSample {
+      left: current.left + (next.left - current.left) * fraction,
+      right: current.right + (next.right - current.right) * fraction,
+    }
+  }
+
+  /// Advance the voice by one output sample, returning false if
+  /// the clip has ended
+  fn advance(&mut self) -> bool {
+    self.position += f64::from(self.playback.pitch.max(0.0));
+
+    let length = self.clip.len() as f64;
+    if self.position < length {
+      true
+    } else if self.playback.looping {
+      self.position %= length;
+      true
+    } else {
+      false
+    }
+  }
+}
+
+/// A handle for playing clips on a `Mixer`
+///
+/// Handles send commands to their mixer over a channel, so they don't
+/// need to lock the mixer, and are cheap to clone. Commands take
+/// effect the next time the mixer synthesizes samples.
+///
+/// Commands are queued until then, so if the mixer isn't being asked
+/// for samples, for example because it is run by a runtime without
+/// audio output, up to `MAX_QUEUED_COMMANDS` commands are kept, and
+/// later ones are discarded.
+#[derive(Clone)]
+pub struct MixerHandle {
+  commands: SyncSender<Command>,
+  next_id: Arc<AtomicUsize>,
+}
+
+impl MixerHandle {
+  /// Play `clip` at its original volume, balance and pitch, returning
+  /// the id of the voice playing it
+  pub fn play(&self, clip: &'static [AudioSample]) -> VoiceId {
+    self.play_with(clip, Playback::default())
+  }
+
+  /// Play `clip` according to `playback`, returning the id of the
+  /// voice playing it
+  pub fn play_with(&self, clip: &'static [AudioSample], playback: Playback) -> VoiceId {
+    let id = VoiceId(self.next_id.fetch_add(1, Ordering::Relaxed));
+    self.send(Command::Play { id, clip, playback });
+    id
+  }
+
+  /// Set the volume of voice `id`
+  pub fn set_volume(&self, id: VoiceId, volume: f32) {
+    self.update(id, Update::Volume(volume));
+  }
+
+  /// Set the stereo balance of voice `id`
+  pub fn set_pan(&self, id: VoiceId, pan: f32) {
+    self.update(id, Update::Pan(pan));
+  }
+
+  /// Set the playback rate of voice `id`
+  pub fn set_pitch(&self, id: VoiceId, pitch: f32) {
+    self.update(id, Update::Pitch(pitch));
+  }
+
+  /// Set whether voice `id` loops its clip
+  pub fn set_looping(&self, id: VoiceId, looping: bool) {
+    self.update(id, Update::Looping(looping));
+  }
+
+  /// Stop voice `id`. Does nothing if it has already stopped.
+  pub fn stop(&self, id: VoiceId) {
+    self.send(Command::Stop { id });
+  }
+
+  /// Stop every voice
+  pub fn stop_all(&self) {
+    self.send(Command::StopAll);
+  }
+
+  fn update(&self, id: VoiceId, update: Update) {
+    self.send(Command::Update { id, update });
+  }
+
+  fn send(&self, command: Command) {
+    // If the mixer has been dropped there is nothing to play
+    // sounds on, and if its queue is full it isn't keeping up or
+    // isn't being run at all, so in either case commands are
+    // discarded instead of blocking
+    self.commands.try_send(command).ok();
+  }
+}
+
+/// A `Synthesizer` that plays sampled audio clips, such as sound
+/// effects and music, mixing together the output of up to
+/// `MAX_VOICES` voices
+///
+/// Clips are slices of samples at `SAMPLES_PER_SECOND`, and are
+/// played by sending commands to the mixer with a `MixerHandle`:
+///
+/// ```
+/// use pxl::*;
+///
+/// static JUMP: [AudioSample; 2] = [
+///   AudioSample {
+///     left: 0.5,
+///     right: 0.5,
+///   },
+///   AudioSample {
+///     left: -0.5,
+///     right: -0.5,
+///   },
+/// ];
+///
+/// let mixer = Mixer::new();
+/// let handle = mixer.handle();
+///
+/// // Return this from `Program::synthesizer`
+/// let synthesizer = Arc::new(Mutex::new(mixer));
+///
+/// // And keep this to play sounds, for example from `Program::tick`
+/// handle.play_with(
+///   &JUMP,
+///   Playback {
+///     volume: 0.5,
+///     ..Playback::default()
+///   },
+/// );
+/// ```
+pub struct Mixer {
+  commands: Receiver<Command>,
+  handle: MixerHandle,
+  voices: Vec<Voice>,
+}
+
+impl Mixer {
+  /// Create a new mixer with no voices playing
+  pub fn new() -> Mixer {
+    let (sender, receiver) = mpsc::sync_channel(MAX_QUEUED_COMMANDS);
+
+    Mixer {
+      commands: receiver,
+      handle: MixerHandle {
+        commands: sender,
+        next_id: Arc::new(AtomicUsize::new(0)),
+      },
+      voices: Vec::with_capacity(MAX_VOICES),
+    }
+  }
+
+  /// Get a handle for playing clips on this mixer
+  pub fn handle(&self) -> MixerHandle {
+    self.handle.clone()
+  }
+
+  /// The number of voices currently playing
+  pub fn voices(&self) -> usize {
+    self.voices.len()
+  }
+
+  fn apply(&mut self, command: Command) {
+    match command {
+      Command::Play { id, clip, playback } => {
+        if clip.is_empty() {
+          return;
+        }
+        if self.voices.len() == MAX_VOICES {
+          self.voices.remove(0);
+        }
+        self.voices.push(Voice {
+          position: 0.0,
+          id,
+          clip,
+          playback,
+        });
+      }
+      Command::Update { id, update } => {
+        if let Some(voice) = self.voices.iter_mut().find(|voice| voice.id == id) {
+          let playback = &mut voice.playback;
+          match update {
+            Update::Volume(volume) => playback.volume = volume,
+            Update::Pan(pan) => playback.pan = pan,
+            Update::Pitch(pitch) => playback.pitch = pitch,
+            Update::Looping(looping) => playback.looping = looping,
+          }
+        }
+      }
+      Command::Stop { id } => self.voices.retain(|voice| voice.id != id),
+      Command::StopAll => self.voices.clear(),
+    }
+  }
+}
+
+impl Default for Mixer {
+  fn default() -> Mixer {
+    Mixer::new()
+  }
+}
+
+impl Synthesizer for Mixer {
+  fn synthesize(&mut self, _samples_played: u64, output_buffer: &mut [AudioSample]) {
+    while let Ok(command) = self.commands.try_recv() {
+      self.apply(command);
+    }
+
+    for output in output_buffer.iter_mut() {
+      *output = AudioSample::default();
+    }
+
+    for voice in &mut self.voices {
+      let (left, right) = Pan::new(voice.playback.pan).gains();
+      let left = left * voice.playback.volume;
+      let right = right * voice.playback.volume;
+
+      for output in output_buffer.iter_mut() {
+        let sample = voice.sample();
+        output.left += sample.left * left;
+        output.right += sample.right * right;
+
+        if !voice.advance() {
+          // Mark the voice as finished
+          voice.clip = &[];
+          break;
+        }
+      }
+    }
+
+    self.voices.retain(|voice| !voice.clip.is_empty());
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn mono(value: f32) -> AudioSample {
+    AudioSample {
+      left: value,
+      right: value,
+    }
+  }
+
+  static RAMP: [AudioSample; 4] = [
+    AudioSample {
+      left: 0.0,
+      right: 0.0,
+    },
+    AudioSample {
+      left: 0.25,
+      right: 0.25,
+    },
+    AudioSample {
+      left: 0.5,
+      right: 0.5,
+    },
+    AudioSample {
+      left: 0.75,
+      right: 0.75,
+    },
+  ];
+
+  fn synthesize(mixer: &mut Mixer, sample_count: usize) -> Vec<f32> {
+    let mut samples = vec![mono(1.0); sample_count];
+    mixer.synthesize(0, &mut samples);
+    samples.iter().map(|sample| sample.left).collect()
+  }
+
+  #[test]
+  fn plays_and_stops() {
+    let mut mixer = Mixer::new();
+    let handle = mixer.handle();
+
+    handle.play(&RAMP);
+    assert_eq!(synthesize(&mut mixer, 6), [0.0, 0.25, 0.5, 0.75, 0.0, 0.0]);
+    assert_eq!(mixer.voices(), 0);
+  }
+
+  #[test]
+  fn mixes_voices() {
+    let mut mixer = Mixer::new();
+    let handle = mixer.handle();
+
+    handle.play(&RAMP);
+    handle.play_with(
+      &RAMP,
+      Playback {
+        volume: 2.0,
+        ..Playback::default()
+      },
+    );
+    assert_eq!(synthesize(&mut mixer, 4), [0.0, 0.75, 1.5, 2.25]);
+  }
+
+  #[test]
+  fn looping_pitch_and_stop() {
+    let mut mixer = Mixer::new();
+    let handle = mixer.handle();
+
+    let id = handle.play_with(
+      &RAMP,
+      Playback {
+        pitch: 1.5,
+        looping: true,
+        ..Playback::default()
+      },
+    );
+    assert_eq!(synthesize(&mut mixer, 4), [0.0, 0.375, 0.75, 0.125]);
+
+    handle.set_pitch(id, 1.0);
+    assert_eq!(synthesize(&mut mixer, 2), [0.5, 0.75]);
+
+    handle.stop(id);
+    assert_eq!(synthesize(&mut mixer, 2), [0.0, 0.0]);
+  }
+
+  #[test]
+  fn pan() {
+    let mut mixer = Mixer::new();
+    let handle = mixer.handle();
+
+    static ONE: [AudioSample; 1] = [AudioSample {
+      left: 1.0,
+      right: 1.0,
+    }];
+
+    handle.play_with(
+      &ONE,
+      Playback {
+        pan: 0.5,
+        ..Playback::default()
+      },
+    );
+
+    let mut samples = [AudioSample::default()];
+    mixer.synthesize(0, &mut samples);
+    assert_eq!(samples[0].left, 0.5);
+    assert_eq!(samples[0].right, 1.0);
+  }
+
+  #[test]
+  fn steals_oldest_voice() {
+    let mut mixer = Mixer::new();
+    let handle = mixer.handle();
+
+    let first = handle.play(&RAMP);
+    for _ in 0..MAX_VOICES {
+      handle.play(&RAMP);
+    }
+    synthesize(&mut mixer, 1);
+    assert_eq!(mixer.voices(), MAX_VOICES);
+    assert!(mixer.voices.iter().all(|voice| voice.id != first));
+  }
+
+  #[test]
+  fn discards_commands_when_full() {
+    let mut mixer = Mixer::new();
+    let handle = mixer.handle();
+
+    let ids = (0..MAX_QUEUED_COMMANDS + 10)
+      .map(|_| handle.play(&RAMP))
+      .collect::<Vec<VoiceId>>();
+    synthesize(&mut mixer, 1);
+
+    // Commands that didn't fit in the queue were discarded, and the
+    // last `MAX_VOICES` of those that did are playing
+    let queued = &ids[MAX_QUEUED_COMMANDS - MAX_VOICES..MAX_QUEUED_COMMANDS];
+    let playing = mixer
+      .voices
+      .iter()
+      .map(|voice| voice.id)
+      .collect::<Vec<VoiceId>>();
+    assert_eq!(playing, queued);
+
+    // Once the queue has been drained, commands are accepted again
+    handle.stop_all();
+    synthesize(&mut mixer, 1);
+    assert_eq!(mixer.voices(), 0);
+  }
+}
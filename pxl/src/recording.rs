@@ -0,0 +1,434 @@
+//! Input recording and replay
+
+use std::{
+  fmt::{self, Display, Formatter}, fs::{self, File}, io::{self, BufWriter, Write},
+  path::{Path, PathBuf}, time::Duration,
+};
+
+use {Button, ButtonState, Error, Event, KeyCode, Modifiers, PointerButton};
+
+/// The first line of every recording file. Bumped whenever the set of
+/// events that can be saved changes, so older versions of pxl reject
+/// recordings containing events they don't understand.
+const HEADER: &str = "pxl-recording 2";
+
+/// Headers of older versions of the format, which this version can
+/// still read, since every line they may contain is still valid
+const COMPATIBLE_HEADERS: &[&str] = &["pxl-recording 1"];
+
+/// The arguments passed to a single call to `Program::tick`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tick {
+  /// The time elapsed since the previous tick
+  pub elapsed: Duration,
+  /// The events that occurred since the previous tick
+  pub events: Vec<Event>,
+}
+
+/// A recording of every call to `Program::tick` made during a run
+/// of a program.
+///
+/// Recordings are produced by `pxl::record` and may be replayed
+/// with `pxl::replay` or `pxl::replay_headless`, in which case the
+/// program receives exactly the same sequence of ticks.
+///
+/// Recordings are saved as text, with one line per tick and one
+/// line per event, so they can be inspected and edited by hand:
+///
+/// ```text
+/// pxl-recording 2
+/// tick 16666666
+/// button up pressed 0
+/// key 32
+/// tick 16666666
+/// button up released 0
+/// ```
+///
+/// `button` lines end with the index of the player the button belongs
+/// to, and `key` lines contain the unicode code point of the character
+/// typed.
+/// Pointer events are saved as `pointer-moved X Y`,
+/// `pointer-button BUTTON STATE X Y` and `scroll X Y`, and
+/// key presses and releases as `key-down KEY MODIFIERS` and
+/// `key-up KEY MODIFIERS`, where `MODIFIERS` is a `+`-separated
+/// list of held modifiers, like `shift+ctrl`, or `-` if none
+/// were held.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Recording {
+  /// The recorded ticks, in order
+  pub ticks: Vec<Tick>,
+}
+
+impl Recording {
+  /// Create a new, empty recording
+  pub fn new() -> Recording {
+    Recording { ticks: Vec::new() }
+  }
+
+  /// Load a recording from the file at `path`
+  pub fn load(path: impl AsRef<Path>) -> Result<Recording, Error> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path).map_err(|io_error| Error::RecordingIo {
+      io_error,
+      path: path.to_path_buf(),
+    })?;
+    Recording::parse(&text).map_err(|line| Error::RecordingParse {
+      path: path.to_path_buf(),
+      line,
+    })
+  }
+
+  /// Save the recording to the file at `path`
+  pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+    let path = path.as_ref();
+    fs::write(path, self.to_string()).map_err(|io_error| Error::RecordingIo {
+      io_error,
+      path: path.to_path_buf(),
+    })
+  }
+
+  /// Parse a recording, returning the number of the first
+  /// invalid line on failure
+  fn parse(text: &str) -> Result<Recording, usize> {
+    let mut lines = text.lines().enumerate();
+
+    match lines.next() {
+      Some((_, header)) if header == HEADER || COMPATIBLE_HEADERS.contains(&header) => {}
+      _ => return Err(1),
+    }
+
+    let mut ticks: Vec<Tick> = Vec::new();
+
+    for (i, line) in lines {
+      let number = i + 1;
+      let words = line.split_whitespace().collect::<Vec<&str>>();
+
+      if words.is_empty() {
+        continue;
+      }
+
+      if let ["tick", nanoseconds] = words.as_slice() {
+        let nanoseconds = nanoseconds.parse::<u64>().map_err(|_| number)?;
+        ticks.push(Tick {
+          elapsed: Duration::new(
+            nanoseconds / 1_000_000_000,
+            (nanoseconds % 1_000_000_000) as u32,
+          ),
+          events: Vec::new(),
+        });
+        continue;
+      }
+
+      let event = parse_event(&words).ok_or(number)?;
+
+      ticks.last_mut().ok_or(number)?.events.push(event);
+    }
+
+    Ok(Recording { ticks })
+  }
+}
+
+fn parse_event(words: &[&str]) -> Option<Event> {
+  match words {
+    ["button", button, state] => Some(Event::Button {
+      button: Button::from_name(button)?,
+      state: parse_state(state)?,
+      player: 0,
+    }),
+    ["button", button, state, player] => Some(Event::Button {
+      button: Button::from_name(button)?,
+      state: parse_state(state)?,
+      player: player.parse().ok()?,
+    }),
+    ["key", code_point] => Some(Event::Key {
+      character: code_point
+        .parse::<u32>()
+        .ok()
+        .and_then(std::char::from_u32)?,
+    }),
+    ["pointer-moved", x, y] => Some(Event::PointerMoved {
+      x: x.parse().ok()?,
+      y: y.parse().ok()?,
+    }),
+    ["pointer-button", button, state, x, y] => Some(Event::PointerButton {
+      button: match *button {
+        "left" => PointerButton::Left,
+        "right" => PointerButton::Right,
+        "middle" => PointerButton::Middle,
+        other => PointerButton::Other(other.parse().ok()?),
+      },
+      state: parse_state(state)?,
+      x: x.parse().ok()?,
+      y: y.parse().ok()?,
+    }),
+    ["scroll", x, y] => Some(Event::Scroll {
+      x: x.parse().ok()?,
+      y: y.parse().ok()?,
+    }),
+    ["key-down", key, modifiers] => Some(Event::KeyDown {
+      key: KeyCode::from_name(key)?,
+      modifiers: parse_modifiers(modifiers)?,
+    }),
+    ["key-up", key, modifiers] => Some(Event::KeyUp {
+      key: KeyCode::from_name(key)?,
+      modifiers: parse_modifiers(modifiers)?,
+    }),
+    _ => None,
+  }
+}
+
+fn parse_modifiers(text: &str) -> Option<Modifiers> {
+  let mut modifiers = Modifiers::default();
+
+  if text == "-" {
+    return Some(modifiers);
+  }
+
+  for modifier in text.split('+') {
+    match modifier {
+      "shift" => modifiers.shift = true,
+      "ctrl" => modifiers.ctrl = true,
+      "alt" => modifiers.alt = true,
+      "logo" => modifiers.logo = true,
+      _ => return None,
+    }
+  }
+
+  Some(modifiers)
+}
+
+struct DisplayModifiers(Modifiers);
+
+impl Display for DisplayModifiers {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    let modifiers = self.0;
+    let names = [
+      (modifiers.shift, "shift"),
+      (modifiers.ctrl, "ctrl"),
+      (modifiers.alt, "alt"),
+      (modifiers.logo, "logo"),
+    ].iter()
+      .filter(|(held, _)| *held)
+      .map(|(_, name)| *name)
+      .collect::<Vec<&str>>();
+
+    if names.is_empty() {
+      write!(f, "-")
+    } else {
+      write!(f, "{}", names.join("+"))
+    }
+  }
+}
+
+fn parse_state(state: &str) -> Option<ButtonState> {
+  match state {
+    "pressed" => Some(ButtonState::Pressed),
+    "released" => Some(ButtonState::Released),
+    _ => None,
+  }
+}
+
+fn state_name(state: ButtonState) -> &'static str {
+  match state {
+    ButtonState::Pressed => "pressed",
+    ButtonState::Released => "released",
+  }
+}
+
+/// Formats a tick as it appears in a recording file
+struct DisplayTick<'a>(&'a Tick);
+
+impl<'a> Display for DisplayTick<'a> {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    let nanoseconds =
+      self.0.elapsed.as_secs() * 1_000_000_000 + u64::from(self.0.elapsed.subsec_nanos());
+    writeln!(f, "tick {}", nanoseconds)?;
+
+    for event in &self.0.events {
+      match event {
+        Event::Button {
+          button,
+          state,
+          player,
+        } => writeln!(
+          f,
+          "button {} {} {}",
+          button.name(),
+          state_name(*state),
+          player
+        )?,
+        Event::Key { character } => writeln!(f, "key {}", *character as u32)?,
+        Event::PointerMoved { x, y } => writeln!(f, "pointer-moved {} {}", x, y)?,
+        Event::PointerButton {
+          button,
+          state,
+          x,
+          y,
+        } => {
+          write!(f, "pointer-button ")?;
+          match button {
+            PointerButton::Left => write!(f, "left")?,
+            PointerButton::Right => write!(f, "right")?,
+            PointerButton::Middle => write!(f, "middle")?,
+            PointerButton::Other(number) => write!(f, "{}", number)?,
+          }
+          writeln!(f, " {} {} {}", state_name(*state), x, y)?;
+        }
+        Event::Scroll { x, y } => writeln!(f, "scroll {} {}", x, y)?,
+        Event::KeyDown { key, modifiers } => writeln!(
+          f,
+          "key-down {} {}",
+          key.name(),
+          DisplayModifiers(*modifiers)
+        )?,
+        Event::KeyUp { key, modifiers } => {
+          writeln!(f, "key-up {} {}", key.name(), DisplayModifiers(*modifiers))?
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl Display for Recording {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    writeln!(f, "{}", HEADER)?;
+
+    for tick in &self.ticks {
+      write!(f, "{}", DisplayTick(tick))?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Writes ticks to a recording file as they happen, so that everything
+/// up to and including the tick being run is saved even if the program
+/// exits with an error or panics
+pub struct Recorder {
+  writer: BufWriter<File>,
+  path: PathBuf,
+}
+
+impl Recorder {
+  /// Create the recording file at `path` and write its header
+  pub fn create(path: PathBuf) -> Result<Recorder, Error> {
+    let mut writer = match File::create(&path) {
+      Ok(file) => BufWriter::new(file),
+      Err(io_error) => return Err(Error::RecordingIo { io_error, path }),
+    };
+
+    match writeln!(writer, "{}", HEADER).and_then(|()| writer.flush()) {
+      Ok(()) => Ok(Recorder { writer, path }),
+      Err(io_error) => Err(Error::RecordingIo { io_error, path }),
+    }
+  }
+
+  fn write(&mut self, tick: &Tick) -> io::Result<()> {
+    write!(self.writer, "{}", DisplayTick(tick))?;
+    self.writer.flush()
+  }
+
+  /// Append `tick` to the recording file
+  pub fn record(&mut self, tick: &Tick) -> Result<(), Error> {
+    self.write(tick).map_err(|io_error| Error::RecordingIo {
+      io_error,
+      path: self.path.clone(),
+    })
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn round_trip() {
+    let recording = Recording {
+      ticks: vec![
+        Tick {
+          elapsed: Duration::new(1, 5),
+          events: vec![
+            Event::Button {
+              button: Button::Action,
+              state: ButtonState::Pressed,
+              player: 0,
+            },
+            Event::Button {
+              button: Button::RightShoulder,
+              state: ButtonState::Released,
+              player: 3,
+            },
+            Event::Key { character: ' ' },
+            Event::Key { character: '☃' },
+            Event::PointerMoved { x: 1.5, y: -0.25 },
+            Event::PointerButton {
+              button: PointerButton::Other(4),
+              state: ButtonState::Released,
+              x: 0.1,
+              y: 100.0,
+            },
+            Event::Scroll { x: 0.0, y: -3.0 },
+            Event::KeyDown {
+              key: KeyCode::PageUp,
+              modifiers: Modifiers {
+                shift: true,
+                ctrl: false,
+                alt: true,
+                logo: false,
+              },
+            },
+            Event::KeyUp {
+              key: KeyCode::Key7,
+              modifiers: Modifiers::default(),
+            },
+          ],
+        },
+        Tick {
+          elapsed: Duration::new(0, 16_666_666),
+          events: Vec::new(),
+        },
+      ],
+    };
+
+    assert_eq!(Recording::parse(&recording.to_string()), Ok(recording));
+  }
+
+  #[test]
+  fn recorder() {
+    let path = std::env::temp_dir().join(format!("pxl-recorder-test-{}", std::process::id()));
+
+    let ticks = vec![
+      Tick {
+        elapsed: Duration::new(0, 1),
+        events: vec![Event::Key { character: 'a' }],
+      },
+      Tick {
+        elapsed: Duration::new(2, 0),
+        events: Vec::new(),
+      },
+    ];
+
+    let mut recorder = Recorder::create(path.clone()).unwrap();
+    recorder.record(&ticks[0]).unwrap();
+    // Ticks are saved as soon as they are recorded
+    assert_eq!(Recording::load(&path).unwrap().ticks, &ticks[..1]);
+    recorder.record(&ticks[1]).unwrap();
+    assert_eq!(Recording::load(&path).unwrap().ticks, ticks);
+
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn parse_errors() {
+    assert_eq!(Recording::parse(""), Err(1));
+    assert_eq!(Recording::parse("pxl-recording 3\n"), Err(1));
+    assert_eq!(Recording::parse("pxl-recording 1\n"), Ok(Recording::new()));
+    assert_eq!(Recording::parse("pxl-recording 1\nkey 32\n"), Err(2));
+    assert_eq!(
+      Recording::parse("pxl-recording 1\ntick 0\nbutton jump pressed\n"),
+      Err(3)
+    );
+  }
+}
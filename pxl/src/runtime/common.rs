@@ -1,18 +1,18 @@
 pub use std::{
-  collections::HashMap, ffi::CString, fmt::{self, Formatter}, mem, ops::DerefMut, os::raw::c_void,
-  ptr, str, thread, time::Instant,
+  collections::HashMap, ffi::CString, fmt::{self, Formatter}, io, mem, ops::DerefMut,
+  os::raw::c_void, path::PathBuf, ptr, str, thread, time::Instant, vec,
 };
 
 pub use *;
 
 pub use runtime::{
-  cpal::{
-    EventLoop, Format, Sample, SampleRate, StreamData, SupportedFormat, UnknownTypeOutputBuffer,
-  },
-  display::Display, error::Error, gl::types::*,
+  cpal::{EventLoop, Sample, StreamData, UnknownTypeOutputBuffer},
+  display::{Display, Surface}, error::Error, gif_capture::GifCapture, gl::types::*,
   glutin::{
-    dpi::{LogicalSize, PhysicalSize}, GlContext, GlWindow,
+    dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize}, GlContext, GlWindow,
   },
-  rustfft::num_traits::Zero as FftZero, rustfft::{num_complex::Complex, FFTplanner},
-  shader_cache::ShaderCache, speaker::Speaker,
+  indexed::Indexed, rustfft::num_traits::Zero as FftZero,
+  renderer::Renderer, resampler::Resampler, rustfft::{num_complex::Complex, FFTplanner},
+  shader_cache::ShaderCache, software::Software, speaker::Speaker, timestep::Timestep,
+  viewport::Viewport,
 };
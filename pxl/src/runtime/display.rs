@@ -9,13 +9,69 @@ static VERTICES: [GLfloat; 24] = [
   0.0, 1.0, 1.0, 1.0, 0.0, 1.0,
 ];
 
-static DEFAULT_VERTEX_SHADER: &str = include_str!("../vertex_shader.glsl");
+pub static DEFAULT_VERTEX_SHADER: &str = include_str!("../vertex_shader.glsl");
 
-static DEFAULT_FRAGMENT_SHADER: &str = include_str!("../fragment_shader.glsl");
+pub static DEFAULT_FRAGMENT_SHADER: &str = include_str!("../fragment_shader.glsl");
+
+/// Fragment shader used to copy the output of the last pass to a
+/// transparent window. Compositors expect premultiplied alpha.
+static PREMULTIPLY_FRAGMENT_SHADER: &str = "
+#version 150
+
+in vec2 uv;
+
+out vec4 color;
+
+uniform sampler2D source;
+
+void main() {
+  vec4 source_color = clamp(texture(source, uv), 0.0, 1.0);
+  color = vec4(source_color.rgb * source_color.a, source_color.a);
+}
+";
+
+/// Fragment shader that replaces each palette index in the `source`
+/// texture with its color in the `palette` texture. Output pixels
+/// are in the same position as their indices.
+static RESOLVE_FRAGMENT_SHADER: &str = "
+#version 150
+
+out vec4 color;
+
+uniform sampler2D source;
+uniform sampler2D palette;
+
+void main() {
+  float index = texelFetch(source, ivec2(gl_FragCoord.xy), 0).r;
+  color = texelFetch(palette, ivec2(int(index * 255.0 + 0.5), 0), 0);
+}
+";
+
+/// The pixels to present
+pub enum Surface<'a> {
+  /// RGBA pixels
+  Rgba(&'a [Pixel]),
+  /// Palette indices, and the palette they index into
+  Indexed {
+    indices: &'a [u8],
+    palette: &'a [Pixel],
+  },
+}
 
 pub struct Display {
   shader_program: u32,
   pixel_texture: u32,
+  index_texture: u32,
+  palette_texture: u32,
+  resolve_program: u32,
+  resolve_texture: u32,
+  /// The resolution that `resolve_texture` was last allocated with
+  resolve_resolution: (usize, usize),
+  resolve_framebuffer: u32,
+  capture_texture: u32,
+  capture_framebuffer: u32,
+  output_texture: u32,
+  transparent: bool,
   sample_texture: u32,
   frequency_texture: u32,
   passthrough_program: u32,
@@ -29,9 +85,16 @@ pub struct Display {
 }
 
 impl Display {
-  pub fn new() -> Result<Display, Error> {
+  /// Create a new display. If `transparent` is true, the window
+  /// is cleared to transparent black, and the alpha of the final
+  /// pass is kept so that the desktop shows through.
+  pub fn new(transparent: bool) -> Result<Display, Error> {
     unsafe {
-      gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+      if transparent {
+        gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+      } else {
+        gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+      }
     }
 
     let mut vao = 0;
@@ -61,6 +124,26 @@ impl Display {
       gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
     }
 
+    let mut index_texture = 0;
+    let mut palette_texture = 0;
+    let mut resolve_texture = 0;
+    let mut capture_texture = 0;
+    unsafe {
+      for texture in &mut [
+        &mut index_texture,
+        &mut palette_texture,
+        &mut resolve_texture,
+        &mut capture_texture,
+      ] {
+        gl::GenTextures(1, &mut **texture);
+        gl::BindTexture(gl::TEXTURE_2D, **texture);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+      }
+    }
+
     let mut sample_texture = 0;
     unsafe {
       gl::GenTextures(1, &mut sample_texture);
@@ -114,10 +197,41 @@ impl Display {
       }
     }
 
+    let mut resolve_framebuffer = 0;
+    let mut capture_framebuffer = 0;
+    for (framebuffer, texture) in &mut [
+      (&mut resolve_framebuffer, resolve_texture),
+      (&mut capture_framebuffer, capture_texture),
+    ] {
+      unsafe {
+        gl::GenFramebuffers(1, &mut **framebuffer);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, **framebuffer);
+        gl::FramebufferTexture(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, *texture, 0);
+        let draw_buffers: [u32; 1] = [gl::COLOR_ATTACHMENT0];
+        gl::DrawBuffers(draw_buffers.len() as i32, draw_buffers.as_ptr());
+      }
+    }
+
     let mut shader_cache = ShaderCache::new();
 
-    let passthrough_program =
-      shader_cache.compile_program(DEFAULT_VERTEX_SHADER, DEFAULT_FRAGMENT_SHADER)?;
+    let resolve_program =
+      shader_cache.compile_program(DEFAULT_VERTEX_SHADER, RESOLVE_FRAGMENT_SHADER)?;
+
+    unsafe {
+      gl::UseProgram(resolve_program);
+      let zpalette = CString::new("palette").unwrap();
+      let palette_uniform = gl::GetUniformLocation(resolve_program, zpalette.as_ptr());
+      gl::Uniform1i(palette_uniform, 4);
+    }
+
+    let passthrough_program = shader_cache.compile_program(
+      DEFAULT_VERTEX_SHADER,
+      if transparent {
+        PREMULTIPLY_FRAGMENT_SHADER
+      } else {
+        DEFAULT_FRAGMENT_SHADER
+      },
+    )?;
 
     Ok(Display {
       shader_program: 0,
@@ -125,6 +239,16 @@ impl Display {
       frame: 0,
       passthrough_program,
       pixel_texture,
+      index_texture,
+      palette_texture,
+      resolve_program,
+      resolve_texture,
+      resolve_resolution: (0, 0),
+      resolve_framebuffer,
+      capture_texture,
+      capture_framebuffer,
+      output_texture: 0,
+      transparent,
       sample_texture,
       frequency_texture,
       shader_cache,
@@ -159,14 +283,89 @@ impl Display {
 
   pub fn present(
     &mut self,
-    pixels: &[Pixel],
+    surface: Surface,
     resolution: (usize, usize),
     window_size: (u32, u32),
+    viewport: Viewport,
     samples: &[AudioSample],
     frequencies: &[Complex<f32>],
   ) {
-    let pixels = pixels.as_ptr();
-    let bytes = pixels as *const c_void;
+    // Palette indices are resolved to colors in a separate pass, the
+    // output of which is used as the first pass's source texture
+    let source_texture = match surface {
+      Surface::Rgba(pixels) => unsafe {
+        gl::ActiveTexture(gl::TEXTURE0);
+        gl::BindTexture(gl::TEXTURE_2D, self.pixel_texture);
+        gl::TexImage2D(
+          gl::TEXTURE_2D,
+          0,
+          gl::RGBA32F as i32,
+          resolution.0 as i32,
+          resolution.1 as i32,
+          0,
+          gl::RGBA,
+          gl::FLOAT,
+          pixels.as_ptr() as *const c_void,
+        );
+        self.pixel_texture
+      },
+      Surface::Indexed { indices, palette } => unsafe {
+        gl::ActiveTexture(gl::TEXTURE0 + 4);
+        gl::BindTexture(gl::TEXTURE_2D, self.palette_texture);
+        gl::TexImage2D(
+          gl::TEXTURE_2D,
+          0,
+          gl::RGBA32F as i32,
+          palette.len() as i32,
+          1,
+          0,
+          gl::RGBA,
+          gl::FLOAT,
+          palette.as_ptr() as *const c_void,
+        );
+
+        gl::ActiveTexture(gl::TEXTURE0);
+        gl::BindTexture(gl::TEXTURE_2D, self.resolve_texture);
+        // The resolve pass overwrites every pixel, so the texture only
+        // needs to be reallocated when the resolution changes
+        if self.resolve_resolution != resolution {
+          gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA32F as i32,
+            resolution.0 as i32,
+            resolution.1 as i32,
+            0,
+            gl::RGBA,
+            gl::FLOAT,
+            ptr::null(),
+          );
+          self.resolve_resolution = resolution;
+        }
+
+        gl::BindTexture(gl::TEXTURE_2D, self.index_texture);
+        // Rows of indices are not padded to four bytes
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        gl::TexImage2D(
+          gl::TEXTURE_2D,
+          0,
+          gl::R8 as i32,
+          resolution.0 as i32,
+          resolution.1 as i32,
+          0,
+          gl::RED,
+          gl::UNSIGNED_BYTE,
+          indices.as_ptr() as *const c_void,
+        );
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+
+        gl::UseProgram(self.resolve_program);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, self.resolve_framebuffer);
+        gl::Viewport(0, 0, resolution.0 as i32, resolution.1 as i32);
+        gl::DrawArrays(gl::TRIANGLES, 0, 6);
+        self.resolve_texture
+      },
+    };
 
     unsafe {
       gl::ActiveTexture(gl::TEXTURE0 + 1);
@@ -237,18 +436,7 @@ impl Display {
 
         gl::ActiveTexture(gl::TEXTURE0);
         if first {
-          gl::BindTexture(gl::TEXTURE_2D, self.pixel_texture);
-          gl::TexImage2D(
-            gl::TEXTURE_2D,
-            0,
-            gl::RGBA32F as i32,
-            resolution.0 as i32,
-            resolution.1 as i32,
-            0,
-            gl::RGBA,
-            gl::FLOAT,
-            bytes,
-          );
+          gl::BindTexture(gl::TEXTURE_2D, source_texture);
         } else {
           gl::BindTexture(gl::TEXTURE_2D, input_texture);
         }
@@ -270,14 +458,23 @@ impl Display {
     }
 
     let input_texture = self.framebuffer_textures[input_index];
+    self.output_texture = input_texture;
 
     unsafe {
       gl::UseProgram(self.passthrough_program);
       gl::ActiveTexture(gl::TEXTURE0);
       gl::BindTexture(gl::TEXTURE_2D, input_texture);
       gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
-      gl::Viewport(0, 0, window_size.0 as i32, window_size.1 as i32);
+      // Clear the whole window, leaving black bars outside of the
+      // viewport, and then draw inside the viewport. GL viewports
+      // are measured from the bottom of the window.
       gl::Clear(gl::COLOR_BUFFER_BIT);
+      gl::Viewport(
+        viewport.x as i32,
+        (f64::from(window_size.1) - viewport.y - viewport.height) as i32,
+        viewport.width as i32,
+        viewport.height as i32,
+      );
       gl::DrawArrays(gl::TRIANGLES, 0, 6);
     }
 
@@ -289,6 +486,89 @@ impl Display {
 
     self.frame += 1;
   }
+
+  /// Read back the most recently presented frame, returning its
+  /// dimensions and its pixels as rows of straight-alpha RGBA bytes,
+  /// from top to bottom. Must be called after `present` and before
+  /// the window's buffers are swapped.
+  pub fn capture(
+    &mut self,
+    screenshot: Screenshot,
+    resolution: (usize, usize),
+    window_size: (u32, u32),
+  ) -> ((usize, usize), Vec<u8>) {
+    let size = match screenshot {
+      Screenshot::ProgramResolution => resolution,
+      Screenshot::WindowResolution => (window_size.0 as usize, window_size.1 as usize),
+    };
+
+    let mut bytes = vec![0; size.0 * size.1 * 4];
+
+    unsafe {
+      match screenshot {
+        // Draw the output of the last pass at its own resolution, in
+        // the same way that it was drawn to the window
+        Screenshot::ProgramResolution => {
+          gl::ActiveTexture(gl::TEXTURE0 + 2);
+          gl::BindTexture(gl::TEXTURE_2D, self.capture_texture);
+          gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA8 as i32,
+            size.0 as i32,
+            size.1 as i32,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            ptr::null(),
+          );
+
+          gl::UseProgram(self.passthrough_program);
+          gl::ActiveTexture(gl::TEXTURE0);
+          gl::BindTexture(gl::TEXTURE_2D, self.output_texture);
+          gl::BindFramebuffer(gl::FRAMEBUFFER, self.capture_framebuffer);
+          gl::Clear(gl::COLOR_BUFFER_BIT);
+          gl::Viewport(0, 0, size.0 as i32, size.1 as i32);
+          gl::DrawArrays(gl::TRIANGLES, 0, 6);
+        }
+        Screenshot::WindowResolution => gl::BindFramebuffer(gl::FRAMEBUFFER, 0),
+      }
+
+      gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+      gl::ReadPixels(
+        0,
+        0,
+        size.0 as i32,
+        size.1 as i32,
+        gl::RGBA,
+        gl::UNSIGNED_BYTE,
+        bytes.as_mut_ptr() as *mut c_void,
+      );
+      gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+      gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+    }
+
+    // GL rows run from bottom to top
+    let mut flipped = bytes
+      .chunks((size.0 * 4).max(1))
+      .rev()
+      .flat_map(|row| row.iter().cloned())
+      .collect::<Vec<u8>>();
+
+    // Transparent windows are drawn with premultiplied alpha
+    if self.transparent {
+      for pixel in flipped.chunks_mut(4) {
+        let alpha = pixel[3];
+        if alpha > 0 {
+          for component in &mut pixel[0..3] {
+            *component = (u32::from(*component) * 255 / u32::from(alpha)).min(255) as u8;
+          }
+        }
+      }
+    }
+
+    (size, flipped)
+  }
 }
 
 impl Drop for Display {
@@ -302,6 +582,12 @@ impl Drop for Display {
         self.framebuffers.len() as i32,
         self.framebuffers.as_mut_ptr(),
       );
+      gl::DeleteTextures(1, &self.index_texture);
+      gl::DeleteTextures(1, &self.palette_texture);
+      gl::DeleteTextures(1, &self.resolve_texture);
+      gl::DeleteFramebuffers(1, &self.resolve_framebuffer);
+      gl::DeleteTextures(1, &self.capture_texture);
+      gl::DeleteFramebuffers(1, &self.capture_framebuffer);
       gl::DeleteBuffers(1, &self.vbo);
       gl::DeleteVertexArrays(1, &self.vao);
       assert_eq!(gl::GetError(), gl::NO_ERROR);
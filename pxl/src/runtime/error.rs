@@ -4,28 +4,122 @@ use runtime::common::*;
 
 use runtime::glutin;
 
+/// Runtime errors
+#[derive(Debug)]
 pub enum Error {
+  /// The default audio output device could not be initialized
   AudioOutputDeviceInitialization,
-  AudioOutputDoesNotSupport48khzSampleRate,
+  /// The window could not be created
   WindowCreation {
+    /// The underlying error
     creation_error: glutin::CreationError,
   },
+  /// The window icon was invalid
+  WindowIcon {
+    /// A description of the problem
+    message: String,
+  },
+  /// The OpenGL graphics context returned an error
   GraphicsContext {
+    /// The underlying error
     context_error: glutin::ContextError,
   },
+  /// A vertex shader failed to compile
   VertexShaderCompilation {
+    /// The vertex shader's source code
     source: String,
+    /// The shader compiler's info log
     info_log: String,
   },
+  /// A fragment shader failed to compile
   FragmentShaderCompilation {
+    /// The fragment shader's source code
     source: String,
+    /// The shader compiler's info log
     info_log: String,
   },
+  /// A shader program failed to link
   ShaderProgramLinking {
+    /// The vertex shader's source code
     vertex_shader_source: String,
+    /// The fragment shader's source code
     fragment_shader_source: String,
+    /// The shader linker's info log
     info_log: String,
   },
+  /// The software presenter was asked to present a frame for a
+  /// program that uses custom vertex, fragment, or filter shaders,
+  /// which require OpenGL
+  SoftwarePresenterRequiresDefaultShaders,
+  /// An error occurred while reading from or writing to the terminal
+  Terminal {
+    /// The underlying error
+    io_error: io::Error,
+  },
+  /// An error occurred while loading or saving a recording
+  RecordingIo {
+    /// The underlying error
+    io_error: io::Error,
+    /// The path of the recording
+    path: PathBuf,
+  },
+  /// A recording could not be parsed
+  RecordingParse {
+    /// The path of the recording
+    path: PathBuf,
+    /// The line number of the first invalid line
+    line: usize,
+  },
+  /// An error occurred while saving a screenshot
+  ScreenshotIo {
+    /// The underlying error
+    io_error: io::Error,
+    /// The path of the screenshot
+    path: PathBuf,
+  },
+  /// An error occurred while saving a captured GIF
+  GifIo {
+    /// The underlying error
+    io_error: io::Error,
+    /// The path of the GIF
+    path: PathBuf,
+  },
+  /// A GIF could not be captured because the frames were larger than
+  /// the maximum GIF dimensions of 65535 by 65535 pixels
+  GifTooLarge {
+    /// The dimensions of the frames
+    size: (usize, usize),
+  },
+  /// An offline render was requested at a frame rate of zero
+  OfflineRenderZeroFrameRate,
+  /// An error occurred while writing the output of an offline render
+  OfflineRenderIo {
+    /// The underlying error
+    io_error: io::Error,
+    /// The path of the file or directory being written
+    path: PathBuf,
+  },
+  /// An error occurred while writing a WAV file
+  WavIo {
+    /// The underlying error
+    io_error: io::Error,
+    /// The path of the WAV file
+    path: PathBuf,
+  },
+  /// An error occurred while loading a bindings file
+  BindingsIo {
+    /// The underlying error
+    io_error: io::Error,
+    /// The path of the bindings file
+    path: PathBuf,
+  },
+  /// A bindings file could not be parsed
+  BindingsParse {
+    /// The path of the bindings file
+    path: PathBuf,
+    /// The line number of the first invalid line
+    line: usize,
+  },
 }
 
 impl From<glutin::CreationError> for Error {
@@ -45,10 +139,8 @@ impl fmt::Display for Error {
     use self::Error::*;
     match self {
       AudioOutputDeviceInitialization => write!(f, "Failed to initialize audio output device."),
-      AudioOutputDoesNotSupport48khzSampleRate => {
-        write!(f, "Audio output device does not support 48khz sample rate")
-      }
       WindowCreation { creation_error } => write!(f, "Failed to create window: {}", creation_error),
+      WindowIcon { message } => write!(f, "Invalid window icon: {}", message),
       GraphicsContext { context_error } => {
         write!(f, "OpenGL graphics context errror: {}", context_error)
       }
@@ -72,6 +164,59 @@ impl fmt::Display for Error {
       Fragment shader source:\n{}\nInfo log:\n{}",
         fragment_shader_source, vertex_shader_source, info_log
       ),
+      SoftwarePresenterRequiresDefaultShaders => write!(
+        f,
+        "Software presenter only supports programs that use the default shaders"
+      ),
+      Terminal { io_error } => write!(f, "Terminal I/O error: {}", io_error),
+      RecordingIo { io_error, path } => write!(
+        f,
+        "I/O error with recording `{}`: {}",
+        path.display(),
+        io_error
+      ),
+      RecordingParse { path, line } => write!(
+        f,
+        "Failed to parse recording `{}`: invalid line {}",
+        path.display(),
+        line
+      ),
+      ScreenshotIo { io_error, path } => write!(
+        f,
+        "I/O error saving screenshot `{}`: {}",
+        path.display(),
+        io_error
+      ),
+      GifIo { io_error, path } => {
+        write!(f, "I/O error saving GIF `{}`: {}", path.display(), io_error)
+      }
+      GifTooLarge { size } => write!(
+        f,
+        "Failed to capture GIF: {}x{} frames are larger than the maximum GIF size",
+        size.0, size.1
+      ),
+      OfflineRenderZeroFrameRate => write!(f, "Offline render frame rate must be nonzero"),
+      OfflineRenderIo { io_error, path } => write!(
+        f,
+        "I/O error writing offline render output `{}`: {}",
+        path.display(),
+        io_error
+      ),
+      WavIo { io_error, path } => {
+        write!(f, "I/O error writing WAV `{}`: {}", path.display(), io_error)
+      }
+      BindingsIo { io_error, path } => write!(
+        f,
+        "I/O error with bindings file `{}`: {}",
+        path.display(),
+        io_error
+      ),
+      BindingsParse { path, line } => write!(
+        f,
+        "Failed to parse bindings file `{}`: invalid line {}",
+        path.display(),
+        line
+      ),
     }
   }
 }
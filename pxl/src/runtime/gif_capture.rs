@@ -0,0 +1,318 @@
+//! Capturing animated GIFs of the presented frames
+
+use runtime::common::*;
+
+use runtime::{
+  color_quant::NeuQuant, gif::{self, SetParameter}, screenshot,
+};
+
+use std::{
+  fs::File, io::BufWriter, sync::mpsc::{self, Sender}, thread::JoinHandle,
+};
+
+/// The shortest delay, in hundredths of a second, between frames.
+/// Many viewers play frames with shorter delays more slowly.
+const MIN_DELAY: u64 = 2;
+
+/// NeuQuant sampling factor, from 1 for best quality to 30 for
+/// fastest quantization
+const SAMPLE_FACTOR: i32 = 10;
+
+/// Whether frames with dimensions `size` fit in a GIF
+fn fits(size: (usize, usize)) -> bool {
+  size.0 <= usize::from(u16::max_value()) && size.1 <= usize::from(u16::max_value())
+}
+
+/// The number of hundredths of a second in `duration`, rounded
+fn centiseconds(duration: Duration) -> u64 {
+  duration.as_secs() * 100 + (u64::from(duration.subsec_nanos()) + 5_000_000) / 10_000_000
+}
+
+/// Reduce `rgba` to at most 256 colors, returning palette indices
+/// and an RGB palette. Frames with 256 or fewer colors are reproduced
+/// exactly, and others are quantized with NeuQuant. Alpha is ignored.
+fn quantize(rgba: &[u8]) -> (Vec<u8>, Vec<u8>) {
+  let mut indices = Vec::with_capacity(rgba.len() / 4);
+  let mut palette = Vec::new();
+  let mut colors = HashMap::new();
+
+  for pixel in rgba.chunks(4) {
+    let color = [pixel[0], pixel[1], pixel[2]];
+    let next = colors.len();
+    if next == 256 && !colors.contains_key(&color) {
+      break;
+    }
+    let index = *colors.entry(color).or_insert_with(|| {
+      palette.extend_from_slice(&color);
+      next as u8
+    });
+    indices.push(index);
+  }
+
+  if indices.len() == rgba.len() / 4 {
+    return (indices, palette);
+  }
+
+  let mut opaque = rgba.to_vec();
+  for pixel in opaque.chunks_mut(4) {
+    pixel[3] = 255;
+  }
+
+  let quantizer = NeuQuant::new(SAMPLE_FACTOR, 256, &opaque);
+
+  let indices = opaque
+    .chunks(4)
+    .map(|pixel| quantizer.index_of(pixel) as u8)
+    .collect();
+
+  (indices, quantizer.color_map_rgb())
+}
+
+struct Frame {
+  indices: Vec<u8>,
+  palette: Vec<u8>,
+}
+
+/// An animated GIF being captured, made of frames at program
+/// resolution, which is written to a file when the capture finishes
+///
+/// Quantizing frames is slow, so it's done on a separate thread, to
+/// avoid slowing down the program while it's being captured.
+pub struct GifCapture {
+  /// The time since the start of the capture at which each frame was
+  /// presented, in hundredths of a second
+  starts: Vec<u64>,
+  size: Option<(usize, usize)>,
+  previous: Vec<u8>,
+  elapsed: Duration,
+  max_duration: Duration,
+  quantizer: Sender<Vec<u8>>,
+  frames: JoinHandle<Vec<Frame>>,
+}
+
+impl GifCapture {
+  pub fn new(max_duration: Duration) -> GifCapture {
+    let (quantizer, receiver) = mpsc::channel::<Vec<u8>>();
+
+    let frames = thread::spawn(move || {
+      receiver
+        .iter()
+        .map(|rgba| {
+          let (indices, palette) = quantize(&rgba);
+          Frame { indices, palette }
+        })
+        .collect()
+    });
+
+    GifCapture {
+      starts: Vec::new(),
+      size: None,
+      previous: Vec::new(),
+      elapsed: Duration::new(0, 0),
+      max_duration,
+      quantizer,
+      frames,
+    }
+  }
+
+  /// Add `rgba`, a frame with dimensions `size`, which was presented
+  /// `elapsed` after the previous frame. Returns false without adding
+  /// the frame if the capture has reached its maximum duration, if
+  /// `size` differs from that of the first frame, or if it is too
+  /// large for a GIF, in which case the capture should be finished.
+  pub fn push(&mut self, elapsed: Duration, size: (usize, usize), rgba: &[u8]) -> bool {
+    if *self.size.get_or_insert(size) != size || !fits(size) {
+      return false;
+    }
+
+    let elapsed = if self.starts.is_empty() {
+      self.elapsed
+    } else {
+      self.elapsed + elapsed
+    };
+
+    if elapsed >= self.max_duration {
+      return false;
+    }
+
+    self.elapsed = elapsed;
+
+    // Skip frames that are identical to the previous frame, or that
+    // follow it too closely for their delay to be represented
+    if let Some(&previous) = self.starts.last() {
+      if rgba == self.previous.as_slice() || centiseconds(elapsed) < previous + MIN_DELAY {
+        return true;
+      }
+    }
+
+    self.starts.push(centiseconds(elapsed));
+
+    self.previous.clear();
+    self.previous.extend_from_slice(rgba);
+
+    // The quantizer only stops when the capture is finished
+    self.quantizer.send(rgba.to_vec()).ok();
+
+    true
+  }
+
+  /// The delay of each frame, in hundredths of a second, if the last
+  /// frame is presented for `elapsed`
+  fn delays(&self, elapsed: Duration) -> Vec<u16> {
+    let end = centiseconds(self.elapsed + elapsed);
+
+    self
+      .starts
+      .iter()
+      .enumerate()
+      .map(|(i, start)| {
+        let next = self.starts.get(i + 1).cloned().unwrap_or(end);
+        next
+          .saturating_sub(*start)
+          .max(MIN_DELAY)
+          .min(u64::from(u16::max_value())) as u16
+      })
+      .collect()
+  }
+
+  /// Finish the capture, with the last frame presented for `elapsed`,
+  /// and write it to a timestamped GIF file in the current directory,
+  /// returning its path. Waits for any frames that haven't been
+  /// quantized yet. If no frames were captured, no file is written
+  /// and `None` is returned.
+  pub fn finish(self, elapsed: Duration) -> Result<Option<PathBuf>, Error> {
+    let size = match self.size {
+      Some(size) if !fits(size) => return Err(Error::GifTooLarge { size }),
+      Some(size) if !self.starts.is_empty() => size,
+      _ => return Ok(None),
+    };
+
+    let path = screenshot::timestamped_path("animation", "gif");
+    let delays = self.delays(elapsed);
+
+    // Closing the channel stops the quantizer once it has quantized
+    // every frame sent to it
+    drop(self.quantizer);
+
+    let io_error = |io_error| Error::GifIo {
+      io_error,
+      path: path.clone(),
+    };
+
+    let frames = self
+      .frames
+      .join()
+      .map_err(|_| io_error(io::Error::new(io::ErrorKind::Other, "quantizer panicked")))?;
+
+    write(&path, size, &frames, &delays).map_err(io_error)?;
+
+    Ok(Some(path))
+  }
+}
+
+fn write(path: &Path, size: (usize, usize), frames: &[Frame], delays: &[u16]) -> io::Result<()> {
+  let file = BufWriter::new(File::create(path)?);
+  let mut encoder = gif::Encoder::new(file, size.0 as u16, size.1 as u16, &[])?;
+  encoder.set(gif::Repeat::Infinite)?;
+
+  for (frame, delay) in frames.iter().zip(delays) {
+    let mut gif_frame = gif::Frame::from_palette_pixels(
+      size.0 as u16,
+      size.1 as u16,
+      &frame.indices,
+      &frame.palette,
+      None,
+    );
+    gif_frame.delay = *delay;
+    encoder.write_frame(&gif_frame)?;
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn millis(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+  }
+
+  #[test]
+  fn exact_palette() {
+    let rgba = [1, 2, 3, 255, 4, 5, 6, 255, 1, 2, 3, 0];
+    assert_eq!(quantize(&rgba), (vec![0, 1, 0], vec![1, 2, 3, 4, 5, 6]));
+  }
+
+  #[test]
+  fn quantized_palette() {
+    let rgba = (0..300u32)
+      .flat_map(|i| vec![i as u8, (i / 256) as u8, 0, 255])
+      .collect::<Vec<u8>>();
+    let (indices, palette) = quantize(&rgba);
+    assert_eq!(indices.len(), 300);
+    assert_eq!(palette.len(), 256 * 3);
+  }
+
+  #[test]
+  fn delays() {
+    let mut capture = GifCapture::new(Duration::from_secs(1));
+    let frame = |value| [value, 0, 0, 255];
+
+    assert!(capture.push(millis(500), (1, 1), &frame(0)));
+    assert!(capture.push(millis(17), (1, 1), &frame(1)));
+    // Too soon after the previous frame
+    assert!(capture.push(millis(5), (1, 1), &frame(2)));
+    assert!(capture.push(millis(28), (1, 1), &frame(3)));
+    // Identical to the previous frame
+    assert!(capture.push(millis(100), (1, 1), &frame(3)));
+    assert!(capture.push(millis(100), (1, 1), &frame(4)));
+
+    assert_eq!(capture.delays(millis(30)), [2, 3, 20, 3]);
+  }
+
+  #[test]
+  fn limits() {
+    let mut capture = GifCapture::new(millis(100));
+    assert!(capture.push(millis(0), (1, 1), &[0, 0, 0, 255]));
+    assert!(!capture.push(millis(0), (2, 1), &[0; 8]));
+    assert!(capture.push(millis(60), (1, 1), &[1, 0, 0, 255]));
+    assert!(!capture.push(millis(60), (1, 1), &[2, 0, 0, 255]));
+    assert_eq!(capture.starts.len(), 2);
+  }
+
+  #[test]
+  fn empty() {
+    let capture = GifCapture::new(millis(100));
+    assert!(capture.finish(millis(0)).unwrap().is_none());
+  }
+
+  #[test]
+  fn too_large() {
+    let mut capture = GifCapture::new(millis(100));
+    let size = (usize::from(u16::max_value()) + 1, 1);
+    assert!(!capture.push(millis(0), size, &[]));
+    match capture.finish(millis(0)) {
+      Err(Error::GifTooLarge { size: error_size }) => assert_eq!(error_size, size),
+      _ => panic!("expected GifTooLarge"),
+    }
+  }
+
+  #[test]
+  fn quantizes_in_background() {
+    let mut capture = GifCapture::new(Duration::from_secs(1));
+    assert!(capture.push(millis(0), (2, 1), &[1, 2, 3, 255, 4, 5, 6, 255]));
+    assert!(capture.push(millis(100), (2, 1), &[4, 5, 6, 255, 4, 5, 6, 255]));
+
+    let GifCapture {
+      quantizer, frames, ..
+    } = capture;
+    drop(quantizer);
+    let frames = frames.join().unwrap();
+
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].indices, [0, 1]);
+    assert_eq!(frames[1].indices, [0, 0]);
+    assert_eq!(frames[1].palette, [4, 5, 6]);
+  }
+}
@@ -0,0 +1,179 @@
+//! Headless runtime, for running programs without a window or GPU
+
+use runtime::common::*;
+
+/// The rate at which the headless runtime's virtual clock advances
+const FRAMES_PER_SECOND: u32 = 60;
+
+/// The number of samples that have played after `clock` has elapsed
+fn samples_due(clock: Duration) -> u64 {
+  let seconds = clock.as_secs() as f64 + f64::from(clock.subsec_nanos()) / 1e9;
+  (seconds * f64::from(SAMPLES_PER_SECOND)).round() as u64
+}
+
+pub struct Headless {
+  program: Box<Program>,
+  synthesizer: Option<Arc<Mutex<Synthesizer>>>,
+  renderer: Renderer,
+  samples_played: u64,
+  current_title: String,
+  software: Option<(Software, (usize, usize))>,
+  replay: Option<vec::IntoIter<Tick>>,
+  clock: Duration,
+  input: Input,
+  timestep: Timestep,
+}
+
+impl Headless {
+  pub fn new(program: Box<Program>) -> Headless {
+    let current_title = program.title().to_string();
+    let synthesizer = program.synthesizer();
+
+    Headless {
+      renderer: Renderer::new(),
+      samples_played: 0,
+      software: None,
+      replay: None,
+      clock: Duration::new(0, 0),
+      input: Input::new(),
+      timestep: Timestep::new(program.tick_rate()),
+      synthesizer,
+      program,
+      current_title,
+    }
+  }
+
+  /// Create a new headless runtime that presents each frame to a
+  /// virtual window of size `window_size` using the software presenter
+  pub fn with_window_size(
+    program: Box<Program>,
+    window_size: (usize, usize),
+  ) -> Result<Headless, Error> {
+    if !Software::supports(program.as_ref()) {
+      return Err(Error::SoftwarePresenterRequiresDefaultShaders);
+    }
+
+    let mut headless = Headless::new(program);
+    headless.software = Some((Software::new(), window_size));
+    Ok(headless)
+  }
+
+  /// Take elapsed time and events from the ticks in `recording`
+  /// instead of the virtual clock, stopping when the recording
+  /// runs out
+  pub fn replay(&mut self, recording: Recording) {
+    self.replay = Some(recording.ticks.into_iter());
+  }
+
+  /// Run the program for at most `frames` frames, returning
+  /// the output of each frame
+  pub fn run(&mut self, frames: usize) -> Vec<Frame> {
+    let frame_duration = Duration::new(0, 1_000_000_000 / FRAMES_PER_SECOND);
+
+    let mut output = Vec::new();
+    let mut should_quit = false;
+
+    while !should_quit && output.len() < frames {
+      let alpha = match self.replay.as_mut().map(|replay| replay.next()) {
+        Some(Some(tick)) => {
+          self.input.set_frame_time(tick.elapsed);
+          self.input.update(&tick.events);
+          self.program.update(tick.elapsed, &self.input);
+          self.clock += tick.elapsed;
+          1.0
+        }
+        Some(None) => break,
+        None => {
+          self.input.set_frame_time(frame_duration);
+          let (ticks, elapsed) = self.timestep.advance(frame_duration);
+          for _ in 0..ticks {
+            self.input.update(&[]);
+            self.program.update(elapsed, &self.input);
+          }
+          self.clock += frame_duration;
+          self.timestep.alpha()
+        }
+      };
+
+      // Synthesize enough samples to catch up with the virtual clock.
+      // Without a synthesizer, the cursor is moved up to the clock
+      // without allocating any samples.
+      let due = samples_due(self.clock);
+
+      let samples = if let Some(ref synthesizer) = self.synthesizer {
+        let mut samples =
+          vec![AudioSample::default(); due.saturating_sub(self.samples_played) as usize];
+        synthesizer
+          .lock()
+          .unwrap()
+          .synthesize(self.samples_played, &mut samples);
+        samples
+      } else {
+        Vec::new()
+      };
+
+      self.samples_played = self.samples_played.max(due);
+
+      let resolution = self.renderer.render(self.program.as_mut(), alpha);
+      let scaling = self.program.scaling();
+      should_quit = self.program.should_quit();
+      let title = self.program.title();
+      if title != self.current_title {
+        self.current_title.clear();
+        self.current_title.push_str(title);
+      }
+
+      let pixels = self.renderer.pixels();
+      let (resolution, pixels) = if let Some((ref mut software, window_size)) = self.software {
+        (
+          window_size,
+          software
+            .present(pixels, resolution, window_size, scaling)
+            .to_vec(),
+        )
+      } else {
+        (resolution, pixels.to_vec())
+      };
+
+      output.push(Frame {
+        title: self.current_title.clone(),
+        resolution,
+        pixels,
+        samples,
+      });
+    }
+
+    output
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  struct Silent;
+
+  impl Program for Silent {
+    fn new() -> Silent {
+      Silent
+    }
+
+    fn resolution(&self) -> (usize, usize) {
+      (1, 1)
+    }
+  }
+
+  #[test]
+  fn sample_cursor_keeps_up_without_synthesizer() {
+    let mut headless = Headless::new(Box::new(Silent));
+
+    for _ in 0..100 {
+      let frames = headless.run(60);
+      assert_eq!(frames.len(), 60);
+      assert!(frames.iter().all(|frame| frame.samples.is_empty()));
+      assert_eq!(headless.samples_played, samples_due(headless.clock));
+    }
+
+    assert!(headless.samples_played >= u64::from(SAMPLES_PER_SECOND) * 99);
+  }
+}
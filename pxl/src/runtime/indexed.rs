@@ -0,0 +1,131 @@
+//! Palette-indexed rendering
+
+use runtime::common::*;
+
+use runtime::DEFAULT_PIXEL;
+
+/// The maximum number of colors in a palette
+pub const PALETTE_SIZE: usize = 256;
+
+/// The palette indices and colors rendered by a program that
+/// uses indexed rendering
+pub struct Indexed {
+  indices: Vec<u8>,
+  palette: Vec<Pixel>,
+}
+
+impl Indexed {
+  pub fn new() -> Indexed {
+    Indexed {
+      indices: Vec::new(),
+      palette: Vec::with_capacity(PALETTE_SIZE),
+    }
+  }
+
+  /// If `program` has a palette, copy it and call `render_indexed`
+  /// with `pixel_count` indices, returning true. Otherwise, return
+  /// false without rendering.
+  pub fn render(&mut self, program: &mut Program, pixel_count: usize) -> bool {
+    match program.palette() {
+      Some(palette) => {
+        self.palette.clear();
+        self
+          .palette
+          .extend(palette.iter().cloned().take(PALETTE_SIZE));
+      }
+      None => return false,
+    }
+
+    // Indices past the end of the program's palette are black
+    self.palette.resize(PALETTE_SIZE, DEFAULT_PIXEL);
+
+    if self.indices.len() != pixel_count {
+      self.indices.resize(pixel_count, 0);
+    }
+
+    program.render_indexed(&mut self.indices);
+
+    true
+  }
+
+  /// The most recently rendered indices
+  pub fn indices(&self) -> &[u8] {
+    &self.indices
+  }
+
+  /// The most recently rendered palette, padded to `PALETTE_SIZE`
+  pub fn palette(&self) -> &[Pixel] {
+    &self.palette
+  }
+
+  /// Look up the color of each index, writing it to `pixels`.
+  /// Used by runtimes that don't resolve colors on the GPU.
+  pub fn resolve(&self, pixels: &mut [Pixel]) {
+    for (pixel, index) in pixels.iter_mut().zip(&self.indices) {
+      *pixel = self.palette[*index as usize];
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  struct Stripes {
+    palette: Vec<Pixel>,
+  }
+
+  impl Program for Stripes {
+    fn new() -> Stripes {
+      Stripes {
+        palette: vec![rgb(1.0, 0.0, 0.0), rgb(0.0, 1.0, 0.0)],
+      }
+    }
+
+    fn resolution(&self) -> (usize, usize) {
+      (3, 1)
+    }
+
+    fn palette(&self) -> Option<&[Pixel]> {
+      Some(&self.palette)
+    }
+
+    fn render_indexed(&mut self, indices: &mut [u8]) {
+      indices.copy_from_slice(&[1, 0, 200]);
+    }
+  }
+
+  #[test]
+  fn resolve() {
+    let red = rgb(1.0, 0.0, 0.0);
+    let green = rgb(0.0, 1.0, 0.0);
+
+    let mut program = Stripes::new();
+
+    let mut indexed = Indexed::new();
+    assert!(indexed.render(&mut program, 3));
+    assert_eq!(indexed.indices(), &[1, 0, 200]);
+    assert_eq!(indexed.palette().len(), PALETTE_SIZE);
+
+    let mut pixels = vec![rgb(1.0, 1.0, 1.0); 3];
+    indexed.resolve(&mut pixels);
+    assert_eq!(pixels, [green, red, DEFAULT_PIXEL]);
+  }
+
+  #[test]
+  fn rgba_programs_are_not_rendered() {
+    struct Rgba;
+
+    impl Program for Rgba {
+      fn new() -> Rgba {
+        Rgba
+      }
+
+      fn resolution(&self) -> (usize, usize) {
+        (1, 1)
+      }
+    }
+
+    assert!(!Indexed::new().render(&mut Rgba, 1));
+  }
+}
@@ -1,18 +1,36 @@
 //! Input and window handling
 
+extern crate color_quant;
 extern crate cpal;
+extern crate gif;
 extern crate gl;
 extern crate glutin;
+extern crate png;
 extern crate rustfft;
 
 mod common;
 mod display;
 mod error;
+mod gif_capture;
+mod headless;
+mod indexed;
+mod offline;
+mod renderer;
+mod resampler;
+mod screenshot;
 mod shader_cache;
+mod software;
 mod speaker;
+mod terminal;
+mod timestep;
+mod viewport;
+
+pub use runtime::{error::Error, headless::Headless, offline::Offline, terminal::Terminal};
 
 use runtime::common::*;
 
+use recording::Recorder;
+
 static DEFAULT_PIXEL: Pixel = Pixel {
   red: 0.0,
   green: 0.0,
@@ -20,43 +38,195 @@ static DEFAULT_PIXEL: Pixel = Pixel {
   alpha: 1.0,
 };
 
-pub struct Runtime {
-  events: Vec<Event>,
-  window_event_loop: glutin::EventsLoop,
-  pixels: Vec<Pixel>,
-  program: Box<Program>,
-  should_quit: bool,
-  gl_window: GlWindow,
-  current_title: String,
-  display: Display,
-  synthesizer_output: Arc<Mutex<Vec<AudioSample>>>,
-  sample_buffer: Vec<AudioSample>,
-  fft_planner: FFTplanner<f32>,
-  fft_input: Vec<Complex<f32>>,
-  fft_output: Vec<Complex<f32>>,
+/// Number of pixels of touchpad scrolling that are
+/// treated as equivalent to scrolling by one line
+const PIXELS_PER_LINE: f64 = 20.0;
+
+fn button_state(state: glutin::ElementState) -> ButtonState {
+  match state {
+    glutin::ElementState::Pressed => ButtonState::Pressed,
+    glutin::ElementState::Released => ButtonState::Released,
+  }
 }
 
-impl Runtime {
-  pub fn new(program: Box<Program>) -> Result<Runtime, Error> {
-    let window_event_loop = glutin::EventsLoop::new();
+fn key_code(virtual_keycode: glutin::VirtualKeyCode) -> Option<KeyCode> {
+  use self::glutin::VirtualKeyCode::*;
+  let key = match virtual_keycode {
+    A => KeyCode::A,
+    B => KeyCode::B,
+    C => KeyCode::C,
+    D => KeyCode::D,
+    E => KeyCode::E,
+    F => KeyCode::F,
+    G => KeyCode::G,
+    H => KeyCode::H,
+    I => KeyCode::I,
+    J => KeyCode::J,
+    K => KeyCode::K,
+    L => KeyCode::L,
+    M => KeyCode::M,
+    N => KeyCode::N,
+    O => KeyCode::O,
+    P => KeyCode::P,
+    Q => KeyCode::Q,
+    R => KeyCode::R,
+    S => KeyCode::S,
+    T => KeyCode::T,
+    U => KeyCode::U,
+    V => KeyCode::V,
+    W => KeyCode::W,
+    X => KeyCode::X,
+    Y => KeyCode::Y,
+    Z => KeyCode::Z,
+    Key0 => KeyCode::Key0,
+    Key1 => KeyCode::Key1,
+    Key2 => KeyCode::Key2,
+    Key3 => KeyCode::Key3,
+    Key4 => KeyCode::Key4,
+    Key5 => KeyCode::Key5,
+    Key6 => KeyCode::Key6,
+    Key7 => KeyCode::Key7,
+    Key8 => KeyCode::Key8,
+    Key9 => KeyCode::Key9,
+    F1 => KeyCode::F1,
+    F2 => KeyCode::F2,
+    F3 => KeyCode::F3,
+    F4 => KeyCode::F4,
+    F5 => KeyCode::F5,
+    F6 => KeyCode::F6,
+    F7 => KeyCode::F7,
+    F8 => KeyCode::F8,
+    F9 => KeyCode::F9,
+    F10 => KeyCode::F10,
+    F11 => KeyCode::F11,
+    F12 => KeyCode::F12,
+    Escape => KeyCode::Escape,
+    Return => KeyCode::Enter,
+    Tab => KeyCode::Tab,
+    Back => KeyCode::Backspace,
+    Space => KeyCode::Space,
+    Up => KeyCode::Up,
+    Down => KeyCode::Down,
+    Left => KeyCode::Left,
+    Right => KeyCode::Right,
+    Insert => KeyCode::Insert,
+    Delete => KeyCode::Delete,
+    Home => KeyCode::Home,
+    End => KeyCode::End,
+    PageUp => KeyCode::PageUp,
+    PageDown => KeyCode::PageDown,
+    _ => return None,
+  };
+  Some(key)
+}
 
-    let current_title = program.title().to_string();
-    let resolution = program.resolution();
-    let synthesizer = program.synthesizer();
+/// Convert `image` to a window icon
+fn window_icon(image: &Image) -> Result<glutin::Icon, Error> {
+  let mut rgba = Vec::with_capacity(image.pixels.len() * 4);
+
+  for pixel in image.pixels {
+    rgba.extend_from_slice(&<[u8; 4]>::from(*pixel));
+  }
+
+  glutin::Icon::from_rgba(rgba, image.width as u32, image.height as u32).map_err(|bad_icon| {
+    Error::WindowIcon {
+      message: bad_icon.to_string(),
+    }
+  })
+}
+
+/// Make `gl_window` fullscreen, borderless, or a normal window
+/// with or without `decorations`. Borderless windows are moved and
+/// resized to cover the monitor, so the window's position and size
+/// are saved in `windowed` when entering borderless and restored
+/// when returning to a normal window.
+fn set_fullscreen(
+  gl_window: &GlWindow,
+  fullscreen: Fullscreen,
+  decorations: bool,
+  windowed: &mut Option<(LogicalPosition, LogicalSize)>,
+) {
+  let monitor = gl_window.get_current_monitor();
+
+  match fullscreen {
+    Fullscreen::Windowed => {
+      gl_window.set_fullscreen(None);
+      gl_window.set_decorations(decorations);
+      if let Some((position, size)) = windowed.take() {
+        gl_window.set_position(position);
+        gl_window.set_inner_size(size);
+      }
+    }
+    Fullscreen::Fullscreen => gl_window.set_fullscreen(Some(monitor)),
+    Fullscreen::Borderless => {
+      if let (Some(position), Some(size)) = (gl_window.get_position(), gl_window.get_inner_size()) {
+        *windowed = Some((position, size));
+      }
+      let hidpi_factor = monitor.get_hidpi_factor();
+      gl_window.set_fullscreen(None);
+      gl_window.set_decorations(false);
+      gl_window.set_position(monitor.get_position().to_logical(hidpi_factor));
+      gl_window.set_inner_size(monitor.get_dimensions().to_logical(hidpi_factor));
+    }
+  }
+}
 
-    // Initially select dimensions using the requested resolution
-    let mut dimensions = LogicalSize::new(resolution.0 as f64, resolution.1 as f64);
+/// Report a failure to save a screenshot or GIF. The program keeps
+/// running, since losing a capture is better than losing the session.
+fn report_capture_error<T>(result: Result<T, Error>) {
+  if let Err(error) = result {
+    eprintln!("{}", error);
+  }
+}
 
-    let window = glutin::WindowBuilder::new()
-      .with_title(current_title.as_str())
-      .with_dimensions(dimensions);
+/// Create a window for `program` with an OpenGL context, sized
+/// and configured according to its `WindowConfig`
+fn open_window(
+  program: &Program,
+  window_event_loop: &glutin::EventsLoop,
+) -> Result<(GlWindow, Display), Error> {
+  let resolution = program.resolution();
+  let frame_pacing = program.frame_pacing();
+  let window_config = program.window_config();
+
+  // Initially select dimensions using the requested resolution
+  let mut dimensions = LogicalSize::new(resolution.0 as f64, resolution.1 as f64);
+
+  let mut window = glutin::WindowBuilder::new()
+    .with_title(program.title())
+    .with_dimensions(dimensions)
+    .with_resizable(window_config.resizable)
+    .with_decorations(window_config.decorations)
+    .with_transparency(window_config.transparent);
+
+  if let Some((width, height)) = window_config.min_size {
+    window = window.with_min_dimensions(LogicalSize::new(width.into(), height.into()));
+  }
 
-    let context = glutin::ContextBuilder::new()
-      .with_double_buffer(Some(true))
-      .with_vsync(true);
+  if let Some((width, height)) = window_config.max_size {
+    window = window.with_max_dimensions(LogicalSize::new(width.into(), height.into()));
+  }
 
-    let gl_window = GlWindow::new(window, context, &window_event_loop)?;
+  if let Some(icon) = window_config.icon {
+    window = window.with_window_icon(Some(window_icon(icon)?));
+  }
+
+  let context = glutin::ContextBuilder::new()
+    .with_double_buffer(Some(true))
+    .with_vsync(frame_pacing.vsync);
+
+  // Transparent windows need a framebuffer with an alpha channel
+  let context = if window_config.transparent {
+    context.with_pixel_format(24, 8)
+  } else {
+    context
+  };
+
+  let gl_window = GlWindow::new(window, context, window_event_loop)?;
 
+  let scale = if let Some(scale) = window_config.scale {
+    scale
+  } else {
     let monitor = gl_window.get_current_monitor();
 
     let mut maximum_dimensions = monitor
@@ -69,20 +239,83 @@ impl Runtime {
 
     // calculate a scaling factor to scale the dimensions up to as
     // large as is allowed by maximum_dimensions
-    let scale = (maximum_dimensions.width / dimensions.width)
+    let mut scale = (maximum_dimensions.width / dimensions.width)
       .min(maximum_dimensions.height / dimensions.height);
 
-    dimensions.width *= scale;
-    dimensions.height *= scale;
+    // with integer scaling, round down to a whole number of
+    // physical pixels per pixel, so the window has no bars
+    if program.scaling() == Scaling::Integer {
+      let hidpi_factor = gl_window.get_hidpi_factor();
+      scale = (scale * hidpi_factor).floor().max(1.0) / hidpi_factor;
+    }
 
-    gl_window.set_inner_size(dimensions);
+    scale
+  };
 
-    unsafe {
-      gl_window.make_current()?;
-      gl::load_with(|symbol| gl_window.get_proc_address(symbol) as *const _);
-    }
+  dimensions.width *= scale;
+  dimensions.height *= scale;
+
+  gl_window.set_inner_size(dimensions);
+
+  unsafe {
+    gl_window.make_current()?;
+    gl::load_with(|symbol| gl_window.get_proc_address(symbol) as *const _);
+  }
+
+  let display = Display::new(window_config.transparent)?;
+
+  Ok((gl_window, display))
+}
+
+pub struct Runtime {
+  events: Vec<Event>,
+  window_event_loop: glutin::EventsLoop,
+  renderer: Renderer,
+  program: Box<Program>,
+  should_quit: bool,
+  gl_window: GlWindow,
+  current_title: String,
+  display: Display,
+  synthesizer_output: Arc<Mutex<Vec<AudioSample>>>,
+  sample_buffer: Vec<AudioSample>,
+  recording: Option<Recorder>,
+  gif_capture: Option<GifCapture>,
+  replay: Option<vec::IntoIter<Tick>>,
+  resolution: (usize, usize),
+  pointer: (f32, f32),
+  input: Input,
+  bindings: Bindings,
+  timestep: Timestep,
+  frame_duration: Option<Duration>,
+  fullscreen: Fullscreen,
+  /// The position and size of the window before it was made
+  /// borderless, restored when it returns to a normal window
+  windowed: Option<(LogicalPosition, LogicalSize)>,
+  /// Whether the last key pressed was Alt+Enter, whose carriage
+  /// return character isn't delivered to the program
+  swallow_return: bool,
+  window_config: WindowConfig,
+}
 
-    let display = Display::new()?;
+impl Runtime {
+  pub fn new(program: Box<Program>) -> Result<Runtime, Error> {
+    let window_event_loop = glutin::EventsLoop::new();
+
+    let current_title = program.title().to_string();
+    let resolution = program.resolution();
+    let synthesizer = program.synthesizer();
+    let frame_pacing = program.frame_pacing();
+    let window_config = program.window_config();
+
+    let (gl_window, display) = open_window(program.as_ref(), &window_event_loop)?;
+
+    let mut windowed = None;
+    set_fullscreen(
+      &gl_window,
+      window_config.fullscreen,
+      window_config.decorations,
+      &mut windowed,
+    );
 
     let synthesizer_output = Arc::new(Mutex::new(Vec::new()));
 
@@ -97,12 +330,22 @@ impl Runtime {
     Ok(Runtime {
       should_quit: false,
       events: Vec::new(),
-      pixels: Vec::new(),
+      renderer: Renderer::new(),
       sample_buffer: Vec::new(),
-      fft_planner: FFTplanner::new(false),
-      fft_input: Vec::new(),
-      fft_output: Vec::new(),
+      recording: None,
+      gif_capture: None,
+      replay: None,
+      pointer: (0.0, 0.0),
+      input: Input::new(),
+      bindings: program.bindings(),
+      timestep: Timestep::new(program.tick_rate()),
+      frame_duration: frame_pacing.frame_duration(),
+      fullscreen: window_config.fullscreen,
+      swallow_return: false,
+      windowed,
+      window_config,
       synthesizer_output,
+      resolution,
       program,
       window_event_loop,
       gl_window,
@@ -111,16 +354,74 @@ impl Runtime {
     })
   }
 
+  /// Record every tick to the file at `path`, writing each tick
+  /// before it is delivered to the program
+  pub fn record(&mut self, path: PathBuf) -> Result<(), Error> {
+    self.recording = Some(Recorder::create(path)?);
+    Ok(())
+  }
+
+  /// Replace keyboard input and elapsed time with the ticks in
+  /// `recording`, exiting when the recording runs out
+  pub fn replay(&mut self, recording: Recording) {
+    self.replay = Some(recording.ticks.into_iter());
+  }
+
+  /// The size of the window's client area in physical pixels
+  fn window_size(&self) -> Option<(u32, u32)> {
+    self.gl_window.get_inner_size().map(|inner_size| {
+      let PhysicalSize { width, height } =
+        inner_size.to_physical(self.gl_window.get_hidpi_factor());
+      (width as u32, height as u32)
+    })
+  }
+
+  /// Deliver pending events to the program and update it
+  fn tick(&mut self, elapsed: Duration) {
+    if let Some(mut recorder) = self.recording.take() {
+      let tick = Tick {
+        events: self.events.clone(),
+        elapsed,
+      };
+
+      // A failure to record shouldn't stop the program being used, so
+      // it's reported and recording stops
+      match recorder.record(&tick) {
+        Ok(()) => self.recording = Some(recorder),
+        Err(error) => eprintln!("{}", error),
+      }
+    }
+
+    self.input.update(&self.events);
+    self.program.update(elapsed, &self.input);
+    self.events.clear();
+  }
+
   pub fn run(mut self) -> Result<(), Error> {
     let start = Instant::now();
     let mut ticked = Duration::new(0, 0);
 
     while !self.should_quit {
+      let frame_start = Instant::now();
       let mut new_size = None;
       let mut should_quit = false;
+      let mut toggle_fullscreen = false;
+      let mut screenshot = None;
+      let mut toggle_gif_capture = false;
+      // Events are only cleared once they have been delivered by a
+      // tick, since a frame may run no ticks with a fixed timestep
       let mut events = mem::replace(&mut self.events, Vec::new());
 
-      events.clear();
+      let hidpi_factor = self.gl_window.get_hidpi_factor();
+      let resolution = self.resolution;
+      let viewport = Viewport::new(
+        self.window_size().unwrap_or((0, 0)),
+        resolution,
+        self.program.scaling(),
+      );
+      let mut pointer = self.pointer;
+      let mut swallow_return = self.swallow_return;
+      let bindings = &self.bindings;
 
       self.window_event_loop.poll_events(|event| {
         use self::glutin::WindowEvent::*;
@@ -129,24 +430,90 @@ impl Runtime {
             CloseRequested => should_quit = true,
             Resized(logical_size) => new_size = Some(logical_size),
             KeyboardInput { input, .. } => if let Some(virtual_keycode) = input.virtual_keycode {
-              use self::glutin::VirtualKeyCode::*;
-              let button = match virtual_keycode {
-                Up => Button::Up,
-                Down => Button::Down,
-                Left => Button::Left,
-                Right => Button::Right,
-                Space => Button::Action,
-                _ => return,
-              };
-
-              use self::glutin::ElementState::*;
-              let state = match input.state {
-                Pressed => ButtonState::Pressed,
-                Released => ButtonState::Released,
-              };
-              events.push(Event::Button { state, button });
+              let state = button_state(input.state);
+
+              // Alt+Enter toggles fullscreen and F12 takes a screenshot or
+              // toggles GIF capture, and neither is delivered to the program
+              let alt_enter =
+                virtual_keycode == glutin::VirtualKeyCode::Return && input.modifiers.alt;
+
+              // Characters are received after the key press that produces
+              // them, so only the carriage return produced by Alt+Enter is
+              // swallowed
+              if state == ButtonState::Pressed {
+                swallow_return = alt_enter;
+              }
+
+              if alt_enter {
+                toggle_fullscreen |= state == ButtonState::Pressed;
+              } else if virtual_keycode == glutin::VirtualKeyCode::F12 {
+                if state == ButtonState::Pressed {
+                  if input.modifiers.ctrl {
+                    toggle_gif_capture = true;
+                  } else {
+                    screenshot = Some(if input.modifiers.shift {
+                      Screenshot::ProgramResolution
+                    } else {
+                      Screenshot::WindowResolution
+                    });
+                  }
+                }
+              } else if let Some(key) = key_code(virtual_keycode) {
+                if let Some(Binding { player, button }) = bindings.get(key) {
+                  events.push(Event::Button {
+                    state,
+                    button,
+                    player,
+                  });
+                }
+
+                let modifiers = Modifiers {
+                  shift: input.modifiers.shift,
+                  ctrl: input.modifiers.ctrl,
+                  alt: input.modifiers.alt,
+                  logo: input.modifiers.logo,
+                };
+                events.push(match state {
+                  ButtonState::Pressed => Event::KeyDown { key, modifiers },
+                  ButtonState::Released => Event::KeyUp { key, modifiers },
+                });
+              }
             },
+            ReceivedCharacter('\r') if swallow_return => swallow_return = false,
             ReceivedCharacter(character) => events.push(Event::Key { character }),
+            CursorMoved { position, .. } => {
+              pointer = viewport.window_to_pixel(position.to_physical(hidpi_factor), resolution);
+              events.push(Event::PointerMoved {
+                x: pointer.0,
+                y: pointer.1,
+              });
+            }
+            MouseInput { state, button, .. } => {
+              use self::glutin::MouseButton;
+              let button = match button {
+                MouseButton::Left => PointerButton::Left,
+                MouseButton::Right => PointerButton::Right,
+                MouseButton::Middle => PointerButton::Middle,
+                MouseButton::Other(number) => PointerButton::Other(number),
+              };
+              events.push(Event::PointerButton {
+                state: button_state(state),
+                x: pointer.0,
+                y: pointer.1,
+                button,
+              });
+            }
+            MouseWheel { delta, .. } => {
+              use self::glutin::MouseScrollDelta::*;
+              let (x, y) = match delta {
+                LineDelta(x, y) => (x, y),
+                PixelDelta(position) => (
+                  (position.x / PIXELS_PER_LINE) as f32,
+                  (position.y / PIXELS_PER_LINE) as f32,
+                ),
+              };
+              events.push(Event::Scroll { x, y });
+            }
             _ => (),
           }
         }
@@ -154,6 +521,24 @@ impl Runtime {
 
       mem::replace(&mut self.events, events);
 
+      self.pointer = pointer;
+      self.swallow_return = swallow_return;
+
+      if toggle_fullscreen {
+        self.fullscreen = match (self.fullscreen, self.window_config.fullscreen) {
+          (Fullscreen::Windowed, Fullscreen::Windowed) => Fullscreen::Fullscreen,
+          (Fullscreen::Windowed, fullscreen) => fullscreen,
+          _ => Fullscreen::Windowed,
+        };
+
+        set_fullscreen(
+          &self.gl_window,
+          self.fullscreen,
+          self.window_config.decorations,
+          &mut self.windowed,
+        );
+      }
+
       if let Some(new_size) = new_size {
         self
           .gl_window
@@ -162,22 +547,30 @@ impl Runtime {
 
       let elapsed = start.elapsed() - ticked;
 
-      self.program.tick(elapsed, &self.events);
-
       ticked += elapsed;
 
-      let resolution = self.program.resolution();
+      self.input.set_frame_time(elapsed);
 
-      let pixel_count = resolution.0 * resolution.1;
-      if self.pixels.len() != pixel_count {
-        self.pixels.resize(pixel_count, DEFAULT_PIXEL);
-      }
+      // Recordings contain ticks, not frames, so replays run one
+      // recorded tick per frame regardless of the timestep
+      let alpha = match self.replay.as_mut().map(|replay| replay.next()) {
+        Some(Some(tick)) => {
+          self.events = tick.events;
+          self.tick(tick.elapsed);
+          1.0
+        }
+        Some(None) => break,
+        None => {
+          let (ticks, elapsed) = self.timestep.advance(elapsed);
+          for _ in 0..ticks {
+            self.tick(elapsed);
+          }
+          self.timestep.alpha()
+        }
+      };
 
-      self.display.set_shaders(
-        self.program.vertex_shader(),
-        self.program.fragment_shader(),
-        self.program.filter_shaders(),
-      )?;
+      let resolution = self.renderer.render(self.program.as_mut(), alpha);
+      self.resolution = resolution;
 
       self.sample_buffer.clear();
 
@@ -186,21 +579,9 @@ impl Runtime {
         &mut self.sample_buffer,
       );
 
-      let fft_size = self.sample_buffer.len();
-      self.fft_output.resize(fft_size, FftZero::zero());
-      self.fft_input.resize(fft_size, FftZero::zero());
-
-      for (input, sample) in self.fft_input.iter_mut().zip(&self.sample_buffer) {
-        *input = Complex::new(sample.left, 0.0);
-      }
-
-      self.fft_output.resize(fft_size, FftZero::zero());
-
-      let fft = self.fft_planner.plan_fft(fft_size);
-      fft.process(&mut self.fft_input, &mut self.fft_output);
-
-      self.program.render(&mut self.pixels);
       self.should_quit = self.program.should_quit() | should_quit;
+      let screenshot = self.program.screenshot().or(screenshot);
+      let toggle_gif_capture = self.program.toggle_gif_capture() | toggle_gif_capture;
       let title = self.program.title();
       if title != self.current_title {
         self.gl_window.set_title(title);
@@ -208,19 +589,52 @@ impl Runtime {
         self.current_title.push_str(&title);
       }
 
-      if let Some(inner_size) = self.gl_window.get_inner_size() {
-        let PhysicalSize { width, height } =
-          inner_size.to_physical(self.gl_window.get_hidpi_factor());
-        self.display.present(
-          &self.pixels,
-          resolution,
-          (width as u32, height as u32),
+      if let Some(window_size) = self.window_size() {
+        self.renderer.present(
+          self.program.as_ref(),
+          &mut self.display,
+          window_size,
+          Viewport::new(window_size, resolution, self.program.scaling()),
           &self.sample_buffer,
-          &self.fft_output[0..self.fft_output.len() / 2],
-        );
+        )?;
+
+        if let Some(screenshot) = screenshot {
+          let (size, rgba) = self.display.capture(screenshot, resolution, window_size);
+          report_capture_error(screenshot::save(size, &rgba));
+        }
+
+        if toggle_gif_capture {
+          match self.gif_capture.take() {
+            Some(gif_capture) => report_capture_error(gif_capture.finish(elapsed)),
+            None => self.gif_capture = Some(GifCapture::new(self.program.max_gif_duration())),
+          }
+        }
+
+        if let Some(mut gif_capture) = self.gif_capture.take() {
+          let (size, rgba) =
+            self
+              .display
+              .capture(Screenshot::ProgramResolution, resolution, window_size);
+          if gif_capture.push(elapsed, size, &rgba) {
+            self.gif_capture = Some(gif_capture);
+          } else {
+            report_capture_error(gif_capture.finish(elapsed));
+          }
+        }
       }
 
       self.gl_window.swap_buffers()?;
+
+      if let Some(frame_duration) = self.frame_duration {
+        let frame_elapsed = frame_start.elapsed();
+        if frame_elapsed < frame_duration {
+          thread::sleep(frame_duration - frame_elapsed);
+        }
+      }
+    }
+
+    if let Some(gif_capture) = self.gif_capture.take() {
+      report_capture_error(gif_capture.finish(Duration::new(0, 0)));
     }
 
     Ok(())
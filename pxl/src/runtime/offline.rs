@@ -0,0 +1,236 @@
+//! Offline runtime, for rendering programs to files slower than
+//! real time
+
+use runtime::common::*;
+
+use runtime::{gl, glutin, screenshot};
+
+use audio;
+
+use std::fs;
+
+/// The number of frames rendered at `frame_rate` frames per second
+/// in `duration`, rounded to the nearest frame
+fn frame_count(duration: Duration, frame_rate: u32) -> u64 {
+  let seconds = duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9;
+  (seconds * f64::from(frame_rate)).round() as u64
+}
+
+/// The number of samples that play during frame `frame` at
+/// `frame_rate` frames per second. Since `SAMPLES_PER_SECOND` may not
+/// be a multiple of `frame_rate`, frames receive either
+/// `SAMPLES_PER_SECOND / frame_rate` samples, rounded down, or one
+/// more, so that the samples of the first `n` frames always add up to
+/// the samples that play in `n / frame_rate` seconds.
+fn frame_samples(frame: u64, frame_rate: u32) -> usize {
+  let due = |frame: u64| frame * u64::from(SAMPLES_PER_SECOND) / u64::from(frame_rate);
+  (due(frame + 1) - due(frame)) as usize
+}
+
+pub struct Offline {
+  program: Box<Program>,
+  synthesizer: Option<Arc<Mutex<Synthesizer>>>,
+  renderer: Renderer,
+  input: Input,
+  timestep: Timestep,
+  display: Display,
+  directory: PathBuf,
+  frame_rate: u32,
+  duration: Duration,
+  // The hidden window and its event loop own the OpenGL context
+  _gl_window: GlWindow,
+  _window_event_loop: glutin::EventsLoop,
+}
+
+impl Offline {
+  /// Create a new offline runtime, which renders `duration` of
+  /// `program` at `frame_rate` frames per second to `directory`,
+  /// using a hidden window for OpenGL. Fails if `frame_rate` is zero.
+  pub fn new(
+    program: Box<Program>,
+    directory: PathBuf,
+    frame_rate: u32,
+    duration: Duration,
+  ) -> Result<Offline, Error> {
+    if frame_rate == 0 {
+      return Err(Error::OfflineRenderZeroFrameRate);
+    }
+
+    let window_event_loop = glutin::EventsLoop::new();
+
+    let resolution = program.resolution();
+
+    let window = glutin::WindowBuilder::new()
+      .with_title(program.title())
+      .with_dimensions(LogicalSize::new(resolution.0 as f64, resolution.1 as f64))
+      .with_visibility(false);
+
+    let context = glutin::ContextBuilder::new().with_vsync(false);
+
+    let gl_window = GlWindow::new(window, context, &window_event_loop)?;
+
+    unsafe {
+      gl_window.make_current()?;
+      gl::load_with(|symbol| gl_window.get_proc_address(symbol) as *const _);
+    }
+
+    let display = Display::new(false)?;
+
+    Ok(Offline {
+      synthesizer: program.synthesizer(),
+      renderer: Renderer::new(),
+      input: Input::new(),
+      timestep: Timestep::new(program.tick_rate()),
+      frame_rate,
+      _gl_window: gl_window,
+      _window_event_loop: window_event_loop,
+      display,
+      directory,
+      duration,
+      program,
+    })
+  }
+
+  /// Render every frame to a numbered PNG file, and, if the program
+  /// has a synthesizer, the audio to `audio.wav`
+  pub fn run(mut self) -> Result<(), Error> {
+    let io_error = |path: &Path| {
+      let path = path.to_path_buf();
+      move |io_error| Error::OfflineRenderIo { io_error, path }
+    };
+
+    fs::create_dir_all(&self.directory).map_err(io_error(&self.directory))?;
+
+    let frame_duration = Duration::new(0, 1_000_000_000 / self.frame_rate);
+
+    let mut audio = Vec::new();
+    let mut samples_played = 0;
+
+    for frame in 0..frame_count(self.duration, self.frame_rate) {
+      self.input.set_frame_time(frame_duration);
+      let (ticks, elapsed) = self.timestep.advance(frame_duration);
+      for _ in 0..ticks {
+        self.input.update(&[]);
+        self.program.update(elapsed, &self.input);
+      }
+      let alpha = self.timestep.alpha();
+
+      // Each frame receives exactly the samples that play during it
+      let mut samples = vec![AudioSample::default(); frame_samples(frame, self.frame_rate)];
+
+      if let Some(ref synthesizer) = self.synthesizer {
+        synthesizer
+          .lock()
+          .unwrap()
+          .synthesize(samples_played, &mut samples);
+      }
+
+      samples_played += samples.len() as u64;
+
+      let resolution = self.renderer.render(self.program.as_mut(), alpha);
+
+      // Frames are captured at program resolution, so the hidden
+      // window's size doesn't matter
+      let window_size = (resolution.0 as u32, resolution.1 as u32);
+
+      self.renderer.present(
+        self.program.as_ref(),
+        &mut self.display,
+        window_size,
+        Viewport::new(window_size, resolution, Scaling::Stretch),
+        &samples,
+      )?;
+
+      let (size, rgba) =
+        self
+          .display
+          .capture(Screenshot::ProgramResolution, resolution, window_size);
+
+      let path = self.directory.join(format!("frame-{:06}.png", frame));
+      screenshot::write(&path, size, &rgba).map_err(io_error(&path))?;
+
+      audio.extend(samples);
+
+      if self.program.should_quit() {
+        break;
+      }
+    }
+
+    if self.synthesizer.is_some() {
+      let path = self.directory.join("audio.wav");
+      audio::write(&path, &audio, WavFormat::Int16).map_err(io_error(&path))?;
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  /// The number of samples in each frame of `duration` at `frame_rate`
+  fn schedule(duration: Duration, frame_rate: u32) -> Vec<usize> {
+    (0..frame_count(duration, frame_rate))
+      .map(|frame| frame_samples(frame, frame_rate))
+      .collect()
+  }
+
+  struct Blank;
+
+  impl Program for Blank {
+    fn new() -> Blank {
+      Blank
+    }
+
+    fn resolution(&self) -> (usize, usize) {
+      (1, 1)
+    }
+  }
+
+  #[test]
+  fn zero_frame_rate() {
+    match Offline::new(Box::new(Blank), PathBuf::new(), 0, Duration::from_secs(1)) {
+      Err(Error::OfflineRenderZeroFrameRate) => {}
+      _ => panic!("zero frame rate accepted"),
+    }
+  }
+
+  #[test]
+  fn even_schedule() {
+    let schedule = schedule(Duration::from_secs(2), 60);
+    assert_eq!(schedule.len(), 120);
+    assert!(schedule.iter().all(|&samples| samples == 800));
+  }
+
+  #[test]
+  fn uneven_schedule() {
+    for &(frame_rate, frames) in &[(44, 132), (7, 21)] {
+      let schedule = schedule(Duration::from_secs(3), frame_rate);
+      assert_eq!(schedule.len(), frames);
+
+      let per_frame = (SAMPLES_PER_SECOND / frame_rate) as usize;
+      let rounded = |&samples: &usize| samples == per_frame || samples == per_frame + 1;
+      assert!(schedule.iter().all(rounded));
+
+      // Every second of frames contains exactly a second of samples
+      for second in schedule.chunks(frame_rate as usize) {
+        assert_eq!(second.iter().sum::<usize>(), SAMPLES_PER_SECOND as usize);
+      }
+    }
+  }
+
+  #[test]
+  fn fractional_duration() {
+    // 3.5 frames round to 4, and the extra sample in each second of
+    // frames isn't due until the seventh
+    assert_eq!(
+      schedule(Duration::from_millis(500), 7),
+      [6857, 6857, 6857, 6857]
+    );
+    assert_eq!(
+      schedule(Duration::from_secs(1), 7),
+      [6857, 6857, 6857, 6857, 6857, 6857, 6858]
+    );
+  }
+}
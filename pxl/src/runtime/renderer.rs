@@ -0,0 +1,109 @@
+//! Rendering a program's frames, shared by every runtime
+
+use runtime::common::*;
+
+use runtime::DEFAULT_PIXEL;
+
+pub struct Renderer {
+  pixels: Vec<Pixel>,
+  indexed: Indexed,
+  /// Whether the most recent frame was rendered with palette indices
+  rendered_indexed: bool,
+  resolution: (usize, usize),
+  fft_planner: FFTplanner<f32>,
+  fft_input: Vec<Complex<f32>>,
+  fft_output: Vec<Complex<f32>>,
+}
+
+impl Renderer {
+  pub fn new() -> Renderer {
+    Renderer {
+      pixels: Vec::new(),
+      indexed: Indexed::new(),
+      rendered_indexed: false,
+      resolution: (0, 0),
+      fft_planner: FFTplanner::new(false),
+      fft_input: Vec::new(),
+      fft_output: Vec::new(),
+    }
+  }
+
+  /// Render a frame of `program` at its current resolution, with
+  /// `render_indexed` if it has a palette, or with
+  /// `render_interpolated` and `alpha` if not, returning the
+  /// frame's resolution
+  pub fn render(&mut self, program: &mut Program, alpha: f64) -> (usize, usize) {
+    let resolution = program.resolution();
+
+    let pixel_count = resolution.0 * resolution.1;
+    if self.pixels.len() != pixel_count {
+      self.pixels.resize(pixel_count, DEFAULT_PIXEL);
+    }
+
+    self.rendered_indexed = self.indexed.render(program, pixel_count);
+    if !self.rendered_indexed {
+      program.render_interpolated(&mut self.pixels, alpha);
+    }
+
+    self.resolution = resolution;
+    resolution
+  }
+
+  /// The pixels of the most recent frame, with palette indices
+  /// resolved to colors. Used by runtimes that don't run shaders.
+  pub fn pixels(&mut self) -> &[Pixel] {
+    if self.rendered_indexed {
+      self.indexed.resolve(&mut self.pixels);
+    }
+    &self.pixels
+  }
+
+  /// Present the most recent frame to `display` with `program`'s
+  /// shaders and filters, which receive `samples` and their
+  /// frequencies
+  pub fn present(
+    &mut self,
+    program: &Program,
+    display: &mut Display,
+    window_size: (u32, u32),
+    viewport: Viewport,
+    samples: &[AudioSample],
+  ) -> Result<(), Error> {
+    display.set_shaders(
+      program.vertex_shader(),
+      program.fragment_shader(),
+      program.filter_shaders(),
+    )?;
+
+    let fft_size = samples.len();
+    self.fft_output.resize(fft_size, FftZero::zero());
+    self.fft_input.resize(fft_size, FftZero::zero());
+
+    for (input, sample) in self.fft_input.iter_mut().zip(samples) {
+      *input = Complex::new(sample.left, 0.0);
+    }
+
+    let fft = self.fft_planner.plan_fft(fft_size);
+    fft.process(&mut self.fft_input, &mut self.fft_output);
+
+    let surface = if self.rendered_indexed {
+      Surface::Indexed {
+        indices: self.indexed.indices(),
+        palette: self.indexed.palette(),
+      }
+    } else {
+      Surface::Rgba(&self.pixels)
+    };
+
+    display.present(
+      surface,
+      self.resolution,
+      window_size,
+      viewport,
+      samples,
+      &self.fft_output[0..fft_size / 2],
+    );
+
+    Ok(())
+  }
+}
@@ -0,0 +1,187 @@
+//! Converting synthesizer output to the output device's sample rate
+//! and channel count
+
+use runtime::common::*;
+
+/// Write `sample` to `frame`, one sample for each of the device's
+/// channels. Mono devices receive the average of both channels.
+/// Channel layouts with more than two channels vary, but the first two
+/// are front left and front right in common layouts, so those receive
+/// the left and right channels, and the rest are silent.
+fn mix(sample: AudioSample, frame: &mut [f32]) {
+  if frame.len() == 1 {
+    frame[0] = (sample.left + sample.right) / 2.0;
+    return;
+  }
+
+  frame[0] = sample.left;
+  frame[1] = sample.right;
+  for channel in &mut frame[2..] {
+    *channel = 0.0;
+  }
+}
+
+/// Converts a stream of samples at `SAMPLES_PER_SECOND` into
+/// interleaved samples at the output device's sample rate, with linear
+/// interpolation, and up-mixes or down-mixes them to the device's
+/// channel count
+///
+/// No anti-aliasing filter is applied, so when downsampling, for
+/// example from 48 kHz to 44.1 kHz, synthesized frequencies above
+/// half the device's sample rate fold back into the audible range as
+/// aliasing. Synthesizers that need clean output at every device rate
+/// should keep their content below about 20 kHz.
+pub struct Resampler {
+  channels: usize,
+  /// The number of input samples per output frame
+  step: f64,
+  /// The position of the next output frame, relative to the start of
+  /// `input`
+  position: f64,
+  /// Synthesized samples that haven't been consumed yet
+  input: Vec<AudioSample>,
+}
+
+impl Resampler {
+  pub fn new(sample_rate: u32, channels: usize) -> Resampler {
+    Resampler {
+      channels: channels.max(1),
+      step: f64::from(SAMPLES_PER_SECOND) / f64::from(sample_rate.max(1)),
+      position: 0.0,
+      input: Vec::new(),
+    }
+  }
+
+  /// Fill `output` with interleaved frames for the device, calling
+  /// `synthesize` to produce as many samples at `SAMPLES_PER_SECOND`
+  /// as are needed. Samples that haven't been consumed are kept for
+  /// the next call.
+  pub fn resample<F>(&mut self, output: &mut [f32], mut synthesize: F)
+  where
+    F: FnMut(&mut [AudioSample]),
+  {
+    let frames = output.len() / self.channels;
+    if frames == 0 {
+      return;
+    }
+
+    // Interpolating the last frame requires the samples on either
+    // side of it
+    let last = self.position + (frames - 1) as f64 * self.step;
+    let required = last as usize + 2;
+
+    if required > self.input.len() {
+      let start = self.input.len();
+      self.input.resize(required, AudioSample::default());
+      synthesize(&mut self.input[start..]);
+    }
+
+    for frame in output.chunks_mut(self.channels).take(frames) {
+      let index = self.position as usize;
+      let fraction = (self.position - index as f64) as f32;
+      let (current, next) = (self.input[index], self.input[index + 1]);
+
+      let sample = AudioSample {
+        left: current.left + (next.left - current.left) * fraction,
+        right: current.right + (next.right - current.right) * fraction,
+      };
+
+      mix(sample, frame);
+
+      self.position += self.step;
+    }
+
+    // When downsampling, the next frame may be past the end of the
+    // input, in which case the samples in between are synthesized and
+    // skipped on the next call
+    let consumed = (self.position as usize).min(self.input.len());
+    self.input.drain(..consumed);
+    self.position -= consumed as f64;
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  /// Resample with a synthesizer that produces an increasing ramp in
+  /// the left channel and twice that in the right, returning the
+  /// output and the number of samples synthesized
+  fn resample(resampler: &mut Resampler, ramp: &mut f32, output_len: usize) -> (Vec<f32>, usize) {
+    let mut output = vec![0.0; output_len];
+    let mut synthesized = 0;
+    resampler.resample(&mut output, |samples| {
+      for sample in samples.iter_mut() {
+        sample.left = *ramp;
+        sample.right = *ramp * 2.0;
+        *ramp += 1.0;
+      }
+      synthesized += samples.len();
+    });
+    (output, synthesized)
+  }
+
+  #[test]
+  fn same_rate() {
+    let mut resampler = Resampler::new(SAMPLES_PER_SECOND, 2);
+    let mut ramp = 0.0;
+
+    let (output, synthesized) = resample(&mut resampler, &mut ramp, 6);
+    assert_eq!(output, [0.0, 0.0, 1.0, 2.0, 2.0, 4.0]);
+    assert_eq!(synthesized, 4);
+
+    let (output, synthesized) = resample(&mut resampler, &mut ramp, 4);
+    assert_eq!(output, [3.0, 6.0, 4.0, 8.0]);
+    assert_eq!(synthesized, 2);
+  }
+
+  #[test]
+  fn upsampling() {
+    let mut resampler = Resampler::new(SAMPLES_PER_SECOND * 2, 1);
+    let mut ramp = 0.0;
+
+    let (output, _) = resample(&mut resampler, &mut ramp, 3);
+    assert_eq!(output, [0.0, 0.75, 1.5]);
+    let (output, _) = resample(&mut resampler, &mut ramp, 3);
+    assert_eq!(output, [2.25, 3.0, 3.75]);
+
+    let mut resampler = Resampler::new(SAMPLES_PER_SECOND * 2, 4);
+    let mut ramp = 0.0;
+
+    let (output, _) = resample(&mut resampler, &mut ramp, 12);
+    assert_eq!(
+      output,
+      [0.0, 0.0, 0.0, 0.0, 0.5, 1.0, 0.0, 0.0, 1.0, 2.0, 0.0, 0.0]
+    );
+    let (output, _) = resample(&mut resampler, &mut ramp, 8);
+    assert_eq!(output, [1.5, 3.0, 0.0, 0.0, 2.0, 4.0, 0.0, 0.0]);
+  }
+
+  #[test]
+  fn downsampling() {
+    let mut resampler = Resampler::new(SAMPLES_PER_SECOND / 3, 2);
+    let mut ramp = 0.0;
+
+    let (output, synthesized) = resample(&mut resampler, &mut ramp, 4);
+    assert_eq!(output, [0.0, 0.0, 3.0, 6.0]);
+    assert_eq!(synthesized, 5);
+
+    let (output, synthesized) = resample(&mut resampler, &mut ramp, 4);
+    assert_eq!(output, [6.0, 12.0, 9.0, 18.0]);
+    assert_eq!(synthesized, 6);
+  }
+
+  #[test]
+  fn fractional_step() {
+    let mut resampler = Resampler::new(32_000, 2);
+    let mut ramp = 0.0;
+
+    let mut output = Vec::new();
+    for _ in 0..3 {
+      output.extend(resample(&mut resampler, &mut ramp, 2).0);
+    }
+
+    let left = output.iter().step_by(2).cloned().collect::<Vec<f32>>();
+    assert_eq!(left, [0.0, 1.5, 3.0]);
+  }
+}
@@ -0,0 +1,113 @@
+//! Saving screenshots as PNG files
+
+use runtime::common::*;
+
+use runtime::png::{self, HasParameters};
+
+use std::{
+  fs::File, io::BufWriter, time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Convert a number of days since the Unix epoch to a
+/// `(year, month, day)` date in the proleptic Gregorian calendar
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+  // Shift the epoch to 0000-03-01, so that leap days fall at
+  // the end of each 400 year era
+  let days = days + 719_468;
+  let era = if days >= 0 { days } else { days - 146_096 } / 146_097;
+  let day_of_era = days - era * 146_097;
+  let year_of_era =
+    (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let shifted_month = (5 * day_of_year + 2) / 153;
+  let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+  let month = if shifted_month < 10 {
+    shifted_month + 3
+  } else {
+    shifted_month - 9
+  } as u32;
+  let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+  (year, month, day)
+}
+
+/// A file name made of `stem`, the UTC time `since_epoch` after
+/// the Unix epoch, and `extension`, e.g.
+/// `screenshot-2018-07-04-13-37-00-000.png`
+fn file_name(stem: &str, extension: &str, since_epoch: Duration) -> String {
+  let seconds = since_epoch.as_secs();
+  let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+  let seconds_of_day = seconds % 86_400;
+
+  format!(
+    "{}-{:04}-{:02}-{:02}-{:02}-{:02}-{:02}-{:03}.{}",
+    stem,
+    year,
+    month,
+    day,
+    seconds_of_day / 3600,
+    seconds_of_day / 60 % 60,
+    seconds_of_day % 60,
+    since_epoch.subsec_millis(),
+    extension,
+  )
+}
+
+/// A path in the current directory made of `stem`, the current
+/// time, and `extension`
+pub fn timestamped_path(stem: &str, extension: &str) -> PathBuf {
+  let since_epoch = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_else(|_| Duration::new(0, 0));
+
+  PathBuf::from(file_name(stem, extension, since_epoch))
+}
+
+/// Write `rgba`, rows of straight-alpha RGBA bytes with dimensions
+/// `size`, to a PNG file at `path`
+pub fn write(path: &Path, size: (usize, usize), rgba: &[u8]) -> io::Result<()> {
+  let file = File::create(path)?;
+  let mut encoder = png::Encoder::new(BufWriter::new(file), size.0 as u32, size.1 as u32);
+  encoder.set(png::ColorType::RGBA).set(png::BitDepth::Eight);
+  let mut writer = encoder.write_header()?;
+  writer.write_image_data(rgba)?;
+  Ok(())
+}
+
+/// Save `rgba`, rows of straight-alpha RGBA bytes with dimensions
+/// `size`, to a timestamped PNG file in the current directory,
+/// returning its path
+pub fn save(size: (usize, usize), rgba: &[u8]) -> Result<PathBuf, Error> {
+  let path = timestamped_path("screenshot", "png");
+
+  write(&path, size, rgba).map_err(|io_error| Error::ScreenshotIo {
+    io_error,
+    path: path.clone(),
+  })?;
+
+  Ok(path)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn dates() {
+    assert_eq!(civil_from_days(0), (1970, 1, 1));
+    assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+    assert_eq!(civil_from_days(17_716), (2018, 7, 4));
+  }
+
+  #[test]
+  fn file_names() {
+    assert_eq!(
+      file_name(
+        "screenshot",
+        "png",
+        Duration::new(1_530_711_420, 123_456_789)
+      ),
+      "screenshot-2018-07-04-13-37-00-123.png"
+    );
+  }
+}
@@ -0,0 +1,164 @@
+//! Software implementation of the default rendering pipeline
+//!
+//! The default vertex and fragment shaders just scale the
+//! program's pixels to the viewport, sampling the nearest
+//! pixel, so programs that don't customize their shaders can
+//! be presented without OpenGL.
+
+use runtime::common::*;
+
+use runtime::{
+  display::{DEFAULT_FRAGMENT_SHADER, DEFAULT_VERTEX_SHADER}, DEFAULT_PIXEL,
+};
+
+pub struct Software {
+  output: Vec<Pixel>,
+}
+
+impl Software {
+  pub fn new() -> Software {
+    Software { output: Vec::new() }
+  }
+
+  /// Returns true if `program` can be presented in software,
+  /// i.e. if it uses the default shaders and no filters
+  pub fn supports(program: &Program) -> bool {
+    program.vertex_shader() == DEFAULT_VERTEX_SHADER
+      && program.fragment_shader() == DEFAULT_FRAGMENT_SHADER
+      && program.filter_shaders().is_empty()
+  }
+
+  /// Scale `pixels`, a buffer of pixels with dimensions `resolution`,
+  /// to `window_size` according to `scaling` using nearest-neighbor
+  /// sampling, returning the scaled pixels
+  pub fn present(
+    &mut self,
+    pixels: &[Pixel],
+    resolution: (usize, usize),
+    window_size: (usize, usize),
+    scaling: Scaling,
+  ) -> &[Pixel] {
+    self.output.clear();
+    self
+      .output
+      .resize(window_size.0 * window_size.1, DEFAULT_PIXEL);
+
+    if resolution.0 == 0 || resolution.1 == 0 {
+      return &self.output;
+    }
+
+    let viewport = Viewport::new(
+      (window_size.0 as u32, window_size.1 as u32),
+      resolution,
+      scaling,
+    );
+
+    let left = viewport.x as usize;
+    let top = viewport.y as usize;
+    let width = viewport.width as usize;
+    let height = viewport.height as usize;
+
+    for y in 0..height {
+      // Sample at the center of each output pixel, like OpenGL does
+      let source_y = ((y * 2 + 1) * resolution.1) / (height * 2);
+      let row = &pixels[source_y * resolution.0..(source_y + 1) * resolution.0];
+      let start = (top + y) * window_size.0 + left;
+      for (x, output) in self.output[start..start + width].iter_mut().enumerate() {
+        let source_x = ((x * 2 + 1) * resolution.0) / (width * 2);
+        *output = row[source_x];
+      }
+    }
+
+    &self.output
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  /// Present a row-major buffer of `resolution` pixels, each a
+  /// distinct gray, to a window of size `window_size`, and return the
+  /// window's rows, with the index of the pixel displayed at each
+  /// position, or `.` where the window shows `DEFAULT_PIXEL`
+  fn present(
+    resolution: (usize, usize),
+    window_size: (usize, usize),
+    scaling: Scaling,
+  ) -> Vec<String> {
+    let pixels = (0..resolution.0 * resolution.1)
+      .map(|i| rgb(0.1, 0.1, 0.1 * (i + 2) as f32))
+      .collect::<Vec<Pixel>>();
+
+    let mut software = Software::new();
+    let output = software.present(&pixels, resolution, window_size, scaling);
+    assert_eq!(output.len(), window_size.0 * window_size.1);
+
+    let symbol = |output: &Pixel| match pixels.iter().position(|pixel| pixel == output) {
+      Some(i) => (b'0' + i as u8) as char,
+      None => {
+        assert_eq!(*output, DEFAULT_PIXEL);
+        '.'
+      }
+    };
+
+    output
+      .chunks(window_size.0.max(1))
+      .map(|row| row.iter().map(&symbol).collect())
+      .collect()
+  }
+
+  #[test]
+  fn integer_upscaling() {
+    assert_eq!(
+      present((2, 2), (4, 4), Scaling::Stretch),
+      ["0011", "0011", "2233", "2233"]
+    );
+  }
+
+  #[test]
+  fn non_integer_ratio() {
+    assert_eq!(
+      present((3, 1), (7, 2), Scaling::Stretch),
+      ["0011122", "0011122"]
+    );
+    assert_eq!(
+      present((3, 2), (4, 5), Scaling::Stretch),
+      ["0112", "0112", "3445", "3445", "3445"]
+    );
+  }
+
+  #[test]
+  fn odd_ratio_letterboxed() {
+    // A scale of 2.5 rounds to a 5x3 viewport, leaving a bar below it
+    assert_eq!(
+      present((2, 1), (5, 4), Scaling::Fit),
+      ["00111", "00111", "00111", "....."]
+    );
+
+    // A scale of 3 leaves bars above and below the viewport
+    assert_eq!(
+      present((1, 1), (3, 5), Scaling::Fit),
+      ["...", "000", "000", "000", "..."]
+    );
+
+    // Integer scaling rounds down to 2, leaving bars to the right of
+    // and below the viewport
+    assert_eq!(
+      present((2, 2), (5, 5), Scaling::Integer),
+      ["0011.", "0011.", "2233.", "2233.", "....."]
+    );
+  }
+
+  #[test]
+  fn zero_sized() {
+    assert_eq!(present((0, 0), (2, 2), Scaling::Fit), ["..", ".."]);
+    assert_eq!(present((0, 3), (2, 1), Scaling::Stretch), [".."]);
+
+    for &scaling in &[Scaling::Stretch, Scaling::Fit, Scaling::Integer] {
+      assert!(present((2, 2), (0, 0), scaling).is_empty());
+      assert!(present((2, 2), (0, 3), scaling).is_empty());
+      assert!(present((2, 2), (3, 0), scaling).is_empty());
+    }
+  }
+}
@@ -7,10 +7,14 @@ use runtime::cpal;
 pub struct Speaker {
   synthesizer: Arc<Mutex<Synthesizer>>,
   synthesizer_output: Arc<Mutex<Vec<AudioSample>>>,
+  resampler: Resampler,
   event_loop: EventLoop,
 }
 
 impl Speaker {
+  /// Open the default output device in its default format. The
+  /// synthesizer always runs at `SAMPLES_PER_SECOND`, and its output
+  /// is resampled to the device's sample rate and channel count.
   pub fn new(
     synthesizer: Arc<Mutex<Synthesizer>>,
     synthesizer_output: Arc<Mutex<Vec<AudioSample>>>,
@@ -19,35 +23,18 @@ impl Speaker {
 
     let device = cpal::default_output_device().ok_or(Error::AudioOutputDeviceInitialization)?;
 
-    let mut supported_output_formats = device
-      .supported_output_formats()
-      .map_err(|_| Error::AudioOutputDeviceInitialization)?
-      .filter(|f| {
-        f.channels == 2
-          && f.min_sample_rate <= SampleRate(SAMPLES_PER_SECOND)
-          && f.max_sample_rate >= SampleRate(SAMPLES_PER_SECOND)
-      })
-      .collect::<Vec<SupportedFormat>>();
-
-    supported_output_formats.sort_unstable_by(|a, b| a.cmp_default_heuristics(b));
-
-    let supported_output_format = supported_output_formats
-      .first()
-      .ok_or(Error::AudioOutputDoesNotSupport48khzSampleRate)?;
-
-    let output_format = Format {
-      channels: 2,
-      sample_rate: SampleRate(SAMPLES_PER_SECOND),
-      data_type: supported_output_format.data_type,
-    };
+    let output_format = device
+      .default_output_format()
+      .map_err(|_| Error::AudioOutputDeviceInitialization)?;
 
     let stream_id = event_loop
       .build_output_stream(&device, &output_format)
-      .unwrap();
+      .map_err(|_| Error::AudioOutputDeviceInitialization)?;
 
     event_loop.play_stream(stream_id);
 
     Ok(Speaker {
+      resampler: Resampler::new(output_format.sample_rate.0, output_format.channels as usize),
       synthesizer,
       synthesizer_output,
       event_loop,
@@ -58,49 +45,36 @@ impl Speaker {
     let synthesizer = self.synthesizer;
     let event_loop = self.event_loop;
     let synthesizer_output = self.synthesizer_output;
-    let mut samples = Vec::new();
+    let mut resampler = self.resampler;
+    let mut output = Vec::new();
     let mut samples_played = 0;
 
     event_loop.run(move |_stream_id, stream_data| {
       if let StreamData::Output { buffer } = stream_data {
-        let sample_count = buffer.len() / 2;
-        samples.clear();
-        samples.resize(
-          sample_count,
-          AudioSample {
-            left: 0.0,
-            right: 0.0,
-          },
-        );
-        synthesizer
-          .lock()
-          .unwrap()
-          .synthesize(samples_played, &mut samples);
-        synthesizer_output.lock().unwrap().extend(&samples);
-        samples_played += sample_count as u64;
+        output.clear();
+        output.resize(buffer.len(), 0.0);
+        resampler.resample(&mut output, |samples| {
+          synthesizer
+            .lock()
+            .unwrap()
+            .synthesize(samples_played, samples);
+          synthesizer_output.lock().unwrap().extend(samples.iter());
+          samples_played += samples.len() as u64;
+        });
         match buffer {
           UnknownTypeOutputBuffer::F32(mut buffer) => {
-            let mut i = 0;
-            for sample in &samples {
-              buffer[i] = sample.left;
-              buffer[i + 1] = sample.right;
-              i += 2;
+            for (sample, value) in buffer.iter_mut().zip(&output) {
+              *sample = *value;
             }
           }
           UnknownTypeOutputBuffer::I16(mut buffer) => {
-            let mut i = 0;
-            for sample in &samples {
-              buffer[i] = sample.left.to_i16();
-              buffer[i + 1] = sample.right.to_i16();
-              i += 2;
+            for (sample, value) in buffer.iter_mut().zip(&output) {
+              *sample = value.to_i16();
             }
           }
           UnknownTypeOutputBuffer::U16(mut buffer) => {
-            let mut i = 0;
-            for sample in &samples {
-              buffer[i] = sample.left.to_u16();
-              buffer[i + 1] = sample.right.to_u16();
-              i += 2;
+            for (sample, value) in buffer.iter_mut().zip(&output) {
+              *sample = value.to_u16();
             }
           }
         }
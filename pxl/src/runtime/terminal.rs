@@ -0,0 +1,589 @@
+//! Terminal runtime, which draws programs with ANSI truecolor
+//! escape codes instead of opening a window
+//!
+//! Each character cell displays two vertically stacked pixels,
+//! using the foreground color for the upper half block glyph
+//! and the background color for the lower half. Shaders are not
+//! run, so the pixels drawn are those written by `render`.
+
+use runtime::common::*;
+
+use std::{
+  fmt::Write as FmtWrite, fs::File, io::{self, Read, Write}, process::{Command, Stdio},
+  sync::mpsc::{self, Receiver},
+};
+
+/// Rate at which frames are drawn. Terminals are slow, so this
+/// is lower than a typical display's refresh rate.
+const FRAMES_PER_SECOND: u32 = 30;
+
+/// Number of frames between checks of the terminal's size
+const SIZE_CHECK_INTERVAL: u64 = 30;
+
+pub struct Terminal {
+  program: Box<Program>,
+  keyboard: Receiver<Vec<u8>>,
+  renderer: Renderer,
+  software: Software,
+  current_title: String,
+  saved_settings: String,
+  synthesizer_output: Arc<Mutex<Vec<AudioSample>>>,
+  output: String,
+  input: Input,
+  bindings: Bindings,
+  timestep: Timestep,
+}
+
+/// Run `stty` with `arguments` on the controlling terminal,
+/// returning its output
+fn stty(arguments: &[&str]) -> Result<String, Error> {
+  let tty = File::open("/dev/tty").map_err(|io_error| Error::Terminal { io_error })?;
+
+  let output = Command::new("stty")
+    .args(arguments)
+    .stdin(tty)
+    .stderr(Stdio::inherit())
+    .output()
+    .map_err(|io_error| Error::Terminal { io_error })?;
+
+  if !output.status.success() {
+    return Err(Error::Terminal {
+      io_error: io::Error::new(io::ErrorKind::Other, "stty failed"),
+    });
+  }
+
+  Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Return the size of the terminal in columns and rows
+fn terminal_size() -> Result<(usize, usize), Error> {
+  let size = stty(&["size"])?;
+  let mut dimensions = size.split_whitespace().map(|n| n.parse::<usize>().ok());
+
+  match (dimensions.next(), dimensions.next()) {
+    (Some(Some(rows)), Some(Some(columns))) => Ok((columns, rows)),
+    _ => Err(Error::Terminal {
+      io_error: io::Error::new(io::ErrorKind::InvalidData, "unexpected output from stty size"),
+    }),
+  }
+}
+
+/// Convert a pixel component to an 8-bit color channel
+fn channel(component: f32) -> u8 {
+  (component.max(0.0).min(1.0) * 255.0).round() as u8
+}
+
+/// Decode the modifier parameter of a control sequence, which is one
+/// more than a bitmask of shift, alt, ctrl, and meta
+fn csi_modifiers(parameter: &str) -> Option<Modifiers> {
+  let bits = parameter.parse::<u8>().ok()?.saturating_sub(1);
+  Some(Modifiers {
+    shift: bits & 1 != 0,
+    alt: bits & 2 != 0,
+    ctrl: bits & 4 != 0,
+    logo: bits & 8 != 0,
+  })
+}
+
+/// Decode the key and modifiers of a control sequence with
+/// `parameters` and `final_byte`, like `1;5` and `A` for Ctrl+Up
+fn csi_key(parameters: &str, final_byte: char) -> Option<(KeyCode, Modifiers)> {
+  let mut parameters = parameters.split(';');
+  let number = parameters.next().unwrap_or("");
+  let modifiers = match parameters.next() {
+    Some(parameter) => csi_modifiers(parameter)?,
+    None => Modifiers::default(),
+  };
+
+  if parameters.next().is_some() {
+    return None;
+  }
+
+  let code = match (final_byte, number) {
+    ('A', "") | ('A', "1") => KeyCode::Up,
+    ('B', "") | ('B', "1") => KeyCode::Down,
+    ('C', "") | ('C', "1") => KeyCode::Right,
+    ('D', "") | ('D', "1") => KeyCode::Left,
+    ('H', "") | ('H', "1") | ('~', "1") => KeyCode::Home,
+    ('F', "") | ('F', "1") | ('~', "4") => KeyCode::End,
+    ('P', "1") => KeyCode::F1,
+    ('Q', "1") => KeyCode::F2,
+    ('R', "1") => KeyCode::F3,
+    ('S', "1") => KeyCode::F4,
+    ('~', "2") => KeyCode::Insert,
+    ('~', "3") => KeyCode::Delete,
+    ('~', "5") => KeyCode::PageUp,
+    ('~', "6") => KeyCode::PageDown,
+    ('~', "15") => KeyCode::F5,
+    ('~', "17") => KeyCode::F6,
+    ('~', "18") => KeyCode::F7,
+    ('~', "19") => KeyCode::F8,
+    ('~', "20") => KeyCode::F9,
+    ('~', "21") => KeyCode::F10,
+    ('~', "23") => KeyCode::F11,
+    ('~', "24") => KeyCode::F12,
+    _ => return None,
+  };
+
+  Some((code, modifiers))
+}
+
+/// Set the terminal's title to `title`, with control characters
+/// removed, since BEL or ESC would end the title early and the rest
+/// would be interpreted by the terminal
+fn set_title(title: &str) {
+  print!("\x1b]0;{}\x07", printable(title));
+}
+
+/// `text` without control characters
+fn printable(text: &str) -> String {
+  text.chars().filter(|character| !character.is_control()).collect()
+}
+
+/// The length of the prefix of `input` that can be decoded without
+/// splitting an escape sequence or a multibyte character, the rest of
+/// which may not have been read yet
+fn complete(input: &[u8]) -> usize {
+  if let Some(escape) = input.iter().rposition(|&byte| byte == b'\x1b') {
+    let sequence = &input[escape + 1..];
+    let incomplete = match sequence.first() {
+      None => true,
+      Some(b'O') => sequence.len() == 1,
+      Some(b'[') => sequence[1..].iter().all(|&byte| match byte {
+        b' '..=b'?' => true,
+        _ => false,
+      }),
+      Some(_) => false,
+    };
+    if incomplete {
+      return escape;
+    }
+  }
+
+  // Continuation bytes are 0b10xxxxxx, and the number of bytes in a
+  // character is determined by its first byte
+  for (index, &byte) in input.iter().enumerate().rev().take(3) {
+    if byte & 0xc0 != 0x80 {
+      let length = match byte {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+      };
+      return if index + length > input.len() {
+        index
+      } else {
+        input.len()
+      };
+    }
+  }
+
+  input.len()
+}
+
+impl Terminal {
+  pub fn new(program: Box<Program>) -> Result<Terminal, Error> {
+    let current_title = program.title().to_string();
+    let synthesizer = program.synthesizer();
+    let bindings = program.bindings();
+    let timestep = Timestep::new(program.tick_rate());
+
+    let saved_settings = stty(&["-g"])?;
+    stty(&["raw", "-echo"])?;
+
+    let (sender, keyboard) = mpsc::channel();
+
+    thread::spawn(move || {
+      let stdin = io::stdin();
+      let mut stdin = stdin.lock();
+      let mut buffer = [0; 64];
+      while let Ok(read) = stdin.read(&mut buffer) {
+        if read == 0 || sender.send(buffer[..read].to_vec()).is_err() {
+          break;
+        }
+      }
+    });
+
+    let synthesizer_output = Arc::new(Mutex::new(Vec::new()));
+
+    // Terminal sessions, especially remote ones, often have no
+    // audio device, so run silently if the speaker can't be opened
+    if let Some(synthesizer) = synthesizer {
+      if let Ok(speaker) = Speaker::new(synthesizer, synthesizer_output.clone()) {
+        thread::spawn(move || {
+          speaker.play();
+        });
+      }
+    }
+
+    // Hide the cursor and clear the screen
+    print!("\x1b[?25l\x1b[2J");
+
+    Ok(Terminal {
+      renderer: Renderer::new(),
+      software: Software::new(),
+      output: String::new(),
+      input: Input::new(),
+      bindings,
+      timestep,
+      keyboard,
+      program,
+      current_title,
+      saved_settings,
+      synthesizer_output,
+    })
+  }
+
+  /// Translate raw bytes read from the terminal into events, removing
+  /// them from `input`. Bytes that may be the start of an escape
+  /// sequence or character that hasn't been completely read yet are
+  /// left in `input`, unless `flush` is true. Returns true if the user
+  /// asked to quit by pressing Ctrl-C.
+  fn parse(input: &mut Vec<u8>, flush: bool, bindings: &Bindings, events: &mut Vec<Event>) -> bool {
+    let mut should_quit = false;
+
+    // Terminals don't report key releases, so buttons and keys are
+    // released immediately after being pressed
+    let key = |events: &mut Vec<Event>, key, modifiers| {
+      if let Some(Binding { player, button }) = bindings.get(key) {
+        for &state in &[ButtonState::Pressed, ButtonState::Released] {
+          events.push(Event::Button {
+            state,
+            button,
+            player,
+          });
+        }
+      }
+      events.push(Event::KeyDown { key, modifiers });
+      events.push(Event::KeyUp { key, modifiers });
+    };
+
+    let none = Modifiers::default();
+
+    let complete = if flush {
+      input.len()
+    } else {
+      complete(input)
+    };
+    let text = String::from_utf8_lossy(&input[..complete]).into_owned();
+    input.drain(..complete);
+
+    let mut characters = text.chars().peekable();
+
+    while let Some(character) = characters.next() {
+      match character {
+        '\x03' => should_quit = true,
+        '\x1b' => match characters.peek().cloned() {
+          Some('[') => {
+            characters.next();
+            // Control sequences are parameter and intermediate bytes
+            // followed by a final byte. Unknown or malformed sequences
+            // are discarded whole.
+            let mut parameters = String::new();
+            let mut final_byte = None;
+            for character in characters.by_ref() {
+              match character {
+                ' '..='?' => parameters.push(character),
+                '@'..='~' => {
+                  final_byte = Some(character);
+                  break;
+                }
+                _ => break,
+              }
+            }
+            if let Some((code, modifiers)) = final_byte.and_then(|b| csi_key(&parameters, b)) {
+              key(events, code, modifiers);
+            }
+          }
+          Some('O') => {
+            characters.next();
+            // Terminals in application cursor mode send arrow, home,
+            // and end keys as SS3 sequences
+            let code = match characters.next() {
+              Some('A') => Some(KeyCode::Up),
+              Some('B') => Some(KeyCode::Down),
+              Some('C') => Some(KeyCode::Right),
+              Some('D') => Some(KeyCode::Left),
+              Some('H') => Some(KeyCode::Home),
+              Some('F') => Some(KeyCode::End),
+              Some('P') => Some(KeyCode::F1),
+              Some('Q') => Some(KeyCode::F2),
+              Some('R') => Some(KeyCode::F3),
+              Some('S') => Some(KeyCode::F4),
+              _ => None,
+            };
+            if let Some(code) = code {
+              key(events, code, none);
+            }
+          }
+          _ => key(events, KeyCode::Escape, none),
+        },
+        ' ' => {
+          key(events, KeyCode::Space, none);
+          events.push(Event::Key { character });
+        }
+        '\r' => {
+          key(events, KeyCode::Enter, none);
+          events.push(Event::Key { character });
+        }
+        '\t' => {
+          key(events, KeyCode::Tab, none);
+          events.push(Event::Key { character });
+        }
+        // Most terminals send DEL for the backspace key
+        '\x7f' => {
+          key(events, KeyCode::Backspace, none);
+          events.push(Event::Key {
+            character: '\x08',
+          });
+        }
+        // Control characters produced by holding control and
+        // pressing a letter
+        '\x01'..='\x1a' => {
+          let letter = (b'a' + character as u8 - 1) as char;
+          if let Some(code) = KeyCode::from_name(&letter.to_string()) {
+            let modifiers = Modifiers {
+              ctrl: true,
+              ..none
+            };
+            key(events, code, modifiers);
+          }
+        }
+        _ => {
+          if character.is_ascii_alphanumeric() {
+            if let Some(code) = KeyCode::from_name(&character.to_string()) {
+              let modifiers = Modifiers {
+                shift: character.is_ascii_uppercase(),
+                ..none
+              };
+              key(events, code, modifiers);
+            }
+          }
+          events.push(Event::Key { character });
+        }
+      }
+    }
+
+    should_quit
+  }
+
+  fn draw(&mut self, resolution: (usize, usize), size: (usize, usize)) -> Result<(), Error> {
+    let window_size = (size.0, size.1 * 2);
+    let scaling = self.program.scaling();
+    let pixels = self
+      .software
+      .present(self.renderer.pixels(), resolution, window_size, scaling);
+
+    self.output.clear();
+    self.output.push_str("\x1b[H");
+
+    for row in 0..size.1 {
+      let top = &pixels[row * 2 * size.0..(row * 2 + 1) * size.0];
+      let bottom = &pixels[(row * 2 + 1) * size.0..(row * 2 + 2) * size.0];
+      for (top, bottom) in top.iter().zip(bottom) {
+        write!(
+          self.output,
+          "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m\u{2580}",
+          channel(top.red),
+          channel(top.green),
+          channel(top.blue),
+          channel(bottom.red),
+          channel(bottom.green),
+          channel(bottom.blue),
+        ).unwrap();
+      }
+      self.output.push_str("\x1b[0m");
+      if row + 1 < size.1 {
+        self.output.push_str("\r\n");
+      }
+    }
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    stdout
+      .write_all(self.output.as_bytes())
+      .and_then(|()| stdout.flush())
+      .map_err(|io_error| Error::Terminal { io_error })
+  }
+
+  pub fn run(mut self) -> Result<(), Error> {
+    // Terminals are drawn no faster than `FRAMES_PER_SECOND`, or
+    // the program's target frame rate, if lower
+    let frame_duration = Duration::new(0, 1_000_000_000 / FRAMES_PER_SECOND).max(
+      self
+        .program
+        .frame_pacing()
+        .frame_duration()
+        .unwrap_or_default(),
+    );
+    let start = Instant::now();
+    let mut ticked = Duration::new(0, 0);
+    let mut input = Vec::new();
+    let mut events = Vec::new();
+    let mut size = terminal_size()?;
+    let mut frame: u64 = 0;
+
+    set_title(&self.current_title);
+
+    loop {
+      let frame_start = Instant::now();
+
+      let mut received = false;
+      while let Ok(bytes) = self.keyboard.try_recv() {
+        input.extend(bytes);
+        received = true;
+      }
+
+      // A lone escape can't be told apart from the start of an escape
+      // sequence until more input arrives, so incomplete input is
+      // parsed anyway if nothing more arrives by the next frame
+      let should_quit = Self::parse(&mut input, !received, &self.bindings, &mut events);
+
+      if frame % SIZE_CHECK_INTERVAL == 0 {
+        let new_size = terminal_size()?;
+        if new_size != size {
+          print!("\x1b[2J");
+          size = new_size;
+        }
+      }
+
+      let elapsed = start.elapsed() - ticked;
+
+      ticked += elapsed;
+
+      self.input.set_frame_time(elapsed);
+
+      // Events are kept until a tick delivers them, since a frame
+      // may run no ticks with a fixed timestep
+      let (ticks, elapsed) = self.timestep.advance(elapsed);
+      for _ in 0..ticks {
+        self.input.update(&events);
+        self.program.update(elapsed, &self.input);
+        events.clear();
+      }
+
+      // Samples are only used by shaders, which the terminal
+      // doesn't run, so discard them
+      self.synthesizer_output.lock().unwrap().clear();
+
+      let alpha = self.timestep.alpha();
+      let resolution = self.renderer.render(self.program.as_mut(), alpha);
+      if self.program.should_quit() | should_quit {
+        break;
+      }
+      let title = self.program.title();
+      if title != self.current_title {
+        set_title(title);
+        self.current_title.clear();
+        self.current_title.push_str(title);
+      }
+
+      self.draw(resolution, size)?;
+
+      frame += 1;
+
+      let frame_elapsed = frame_start.elapsed();
+      if frame_elapsed < frame_duration {
+        thread::sleep(frame_duration - frame_elapsed);
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl Drop for Terminal {
+  fn drop(&mut self) {
+    // Reset colors, clear the screen, and show the cursor
+    print!("\x1b[0m\x1b[2J\x1b[H\x1b[?25h");
+    io::stdout().flush().ok();
+    stty(&[&self.saved_settings]).ok();
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn parse(bytes: &[u8]) -> Vec<Event> {
+    parse_reads(&[bytes])
+  }
+
+  /// Parse `reads` as separate reads from the terminal, flushing
+  /// after the last
+  fn parse_reads(reads: &[&[u8]]) -> Vec<Event> {
+    let mut input = Vec::new();
+    let mut events = Vec::new();
+    for read in reads {
+      input.extend_from_slice(read);
+      Terminal::parse(&mut input, false, &Bindings::new(), &mut events);
+    }
+    Terminal::parse(&mut input, true, &Bindings::new(), &mut events);
+    assert!(input.is_empty());
+    events
+  }
+
+  fn key(key: KeyCode, modifiers: Modifiers) -> Vec<Event> {
+    vec![
+      Event::KeyDown { key, modifiers },
+      Event::KeyUp { key, modifiers },
+    ]
+  }
+
+  #[test]
+  fn modified_keys() {
+    let ctrl = Modifiers {
+      ctrl: true,
+      ..Modifiers::default()
+    };
+    assert_eq!(parse(b"\x1b[1;5A"), key(KeyCode::Up, ctrl));
+
+    let shift = Modifiers {
+      shift: true,
+      ..Modifiers::default()
+    };
+    assert_eq!(parse(b"\x1b[1;2C"), key(KeyCode::Right, shift));
+    assert_eq!(parse(b"\x1b[3;2~"), key(KeyCode::Delete, shift));
+    assert_eq!(parse(b"\x1b[A"), key(KeyCode::Up, Modifiers::default()));
+
+    // Application cursor mode
+    assert_eq!(parse(b"\x1bOA"), key(KeyCode::Up, Modifiers::default()));
+    assert_eq!(parse(b"\x1bOD"), key(KeyCode::Left, Modifiers::default()));
+  }
+
+  #[test]
+  fn unknown_sequences() {
+    let mut expected = key(KeyCode::X, Modifiers::default());
+    expected.push(Event::Key { character: 'x' });
+
+    assert_eq!(parse(b"\x1b[200~x"), expected);
+    assert_eq!(parse(b"\x1b[1;2;3Ax"), expected);
+    assert_eq!(parse(b"\x1bOZx"), expected);
+  }
+
+  #[test]
+  fn title_control_characters() {
+    assert_eq!(printable("a\x07b\x1b]0;c\x1b\\d\u{9b}e"), "ab]0;c\\de");
+    assert_eq!(printable("Life \u{2014} 60 fps"), "Life \u{2014} 60 fps");
+  }
+
+  #[test]
+  fn split_reads() {
+    let none = Modifiers::default();
+
+    assert_eq!(parse_reads(&[b"\x1b", b"[A"]), key(KeyCode::Up, none));
+    assert_eq!(parse_reads(&[b"\x1b[1;", b"5A"]), parse(b"\x1b[1;5A"));
+    assert_eq!(parse_reads(&[b"\x1bO", b"B"]), key(KeyCode::Down, none));
+
+    let e_acute = "\u{e9}".as_bytes();
+    assert_eq!(
+      parse_reads(&[&e_acute[..1], &e_acute[1..]]),
+      [Event::Key {
+        character: '\u{e9}',
+      }]
+    );
+
+    // Escape on its own is flushed as the escape key
+    assert_eq!(parse_reads(&[b"\x1b"]), key(KeyCode::Escape, none));
+  }
+}
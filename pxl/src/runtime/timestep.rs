@@ -0,0 +1,108 @@
+//! Conversion of frame durations into ticks
+
+use runtime::common::*;
+
+/// The maximum number of ticks run per frame when using a fixed
+/// timestep. If a frame takes so long that more ticks are due, the
+/// excess time is dropped so that the program slows down instead
+/// of falling further and further behind.
+const MAX_TICKS_PER_FRAME: u32 = 8;
+
+fn seconds(duration: Duration) -> f64 {
+  duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9
+}
+
+pub struct Timestep {
+  step: Option<Duration>,
+  accumulated: Duration,
+}
+
+impl Timestep {
+  /// Create a new timestep that runs ticks of duration `step`,
+  /// or one tick per frame if `step` is `None`
+  pub fn new(step: Option<Duration>) -> Timestep {
+    Timestep {
+      step: step.filter(|step| *step > Duration::new(0, 0)),
+      accumulated: Duration::new(0, 0),
+    }
+  }
+
+  /// Advance by a frame that took `elapsed`, returning the number of
+  /// ticks to run and the duration to pass to each
+  pub fn advance(&mut self, elapsed: Duration) -> (u32, Duration) {
+    let step = match self.step {
+      Some(step) => step,
+      None => return (1, elapsed),
+    };
+
+    self.accumulated += elapsed;
+
+    let mut ticks = 0;
+    while self.accumulated >= step && ticks < MAX_TICKS_PER_FRAME {
+      self.accumulated -= step;
+      ticks += 1;
+    }
+
+    if self.accumulated >= step {
+      let excess = seconds(self.accumulated) % seconds(step);
+      self.accumulated = Duration::new(excess.trunc() as u64, (excess.fract() * 1e9) as u32);
+    }
+
+    (ticks, step)
+  }
+
+  /// The fraction of a tick that has accumulated but not yet been
+  /// run, for use as the `alpha` passed to `Program::render_interpolated`
+  pub fn alpha(&self) -> f64 {
+    match self.step {
+      Some(step) => seconds(self.accumulated) / seconds(step),
+      None => 1.0,
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn milliseconds(milliseconds: u32) -> Duration {
+    Duration::new(0, milliseconds * 1_000_000)
+  }
+
+  #[test]
+  fn variable() {
+    let mut timestep = Timestep::new(None);
+    assert_eq!(timestep.advance(milliseconds(7)), (1, milliseconds(7)));
+    assert_eq!(timestep.alpha(), 1.0);
+  }
+
+  #[test]
+  fn fixed() {
+    let mut timestep = Timestep::new(Some(milliseconds(10)));
+    assert_eq!(timestep.advance(milliseconds(4)), (0, milliseconds(10)));
+    assert!((timestep.alpha() - 0.4).abs() < 1e-9);
+    assert_eq!(timestep.advance(milliseconds(25)), (2, milliseconds(10)));
+    assert!((timestep.alpha() - 0.9).abs() < 1e-9);
+  }
+
+  #[test]
+  fn spiral_of_death() {
+    let mut timestep = Timestep::new(Some(milliseconds(10)));
+    assert_eq!(
+      timestep.advance(milliseconds(1005)),
+      (MAX_TICKS_PER_FRAME, milliseconds(10))
+    );
+    assert!((timestep.alpha() - 0.5).abs() < 1e-6);
+    assert_eq!(timestep.advance(milliseconds(0)), (0, milliseconds(10)));
+  }
+
+  #[test]
+  fn long_step() {
+    let mut timestep = Timestep::new(Some(Duration::from_secs(10)));
+    assert_eq!(
+      timestep.advance(Duration::from_secs(95)),
+      (MAX_TICKS_PER_FRAME, Duration::from_secs(10))
+    );
+    assert!((timestep.alpha() - 0.5).abs() < 1e-6);
+  }
+}
@@ -0,0 +1,135 @@
+//! The region of the window that displays the program's pixels
+
+use runtime::common::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Viewport {
+  /// Horizontal offset of the viewport from the left edge
+  /// of the window, in physical pixels
+  pub x: f64,
+  /// Vertical offset of the viewport from the top edge
+  /// of the window, in physical pixels
+  pub y: f64,
+  /// Width of the viewport in physical pixels
+  pub width: f64,
+  /// Height of the viewport in physical pixels
+  pub height: f64,
+}
+
+impl Viewport {
+  /// Calculate the viewport for a pixel surface with dimensions
+  /// `resolution`, scaled according to `scaling`, in a window of
+  /// size `window_size`. The viewport's edges are always aligned
+  /// to whole physical pixels.
+  pub fn new(window_size: (u32, u32), resolution: (usize, usize), scaling: Scaling) -> Viewport {
+    let window_width = f64::from(window_size.0);
+    let window_height = f64::from(window_size.1);
+
+    let full = Viewport {
+      x: 0.0,
+      y: 0.0,
+      width: window_width,
+      height: window_height,
+    };
+
+    if resolution.0 == 0 || resolution.1 == 0 {
+      return full;
+    }
+
+    let resolution_width = resolution.0 as f64;
+    let resolution_height = resolution.1 as f64;
+
+    let fit = (window_width / resolution_width).min(window_height / resolution_height);
+
+    let scale = match scaling {
+      Scaling::Stretch => return full,
+      Scaling::Fit => fit,
+      // Windows too small for even one physical pixel per pixel
+      // fall back to fitting, so that the whole surface is visible
+      Scaling::Integer => if fit >= 1.0 {
+        fit.floor()
+      } else {
+        fit
+      },
+    };
+
+    let width = (resolution_width * scale).round();
+    let height = (resolution_height * scale).round();
+
+    Viewport {
+      x: ((window_width - width) / 2.0).floor(),
+      y: ((window_height - height) / 2.0).floor(),
+      width,
+      height,
+    }
+  }
+
+  /// Convert `position`, in physical window coordinates, to the
+  /// coordinates of a pixel surface with dimensions `resolution`.
+  /// Positions outside of the viewport, for example in letterbox
+  /// bars, produce coordinates outside of the pixel surface.
+  pub fn window_to_pixel(
+    &self,
+    position: PhysicalPosition,
+    resolution: (usize, usize),
+  ) -> (f32, f32) {
+    let x = (position.x - self.x) / self.width * resolution.0 as f64;
+    let y = (position.y - self.y) / self.height * resolution.1 as f64;
+    (x as f32, y as f32)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn viewport(x: f64, y: f64, width: f64, height: f64) -> Viewport {
+    Viewport {
+      x,
+      y,
+      width,
+      height,
+    }
+  }
+
+  #[test]
+  fn stretch() {
+    assert_eq!(
+      Viewport::new((300, 100), (10, 10), Scaling::Stretch),
+      viewport(0.0, 0.0, 300.0, 100.0)
+    );
+  }
+
+  #[test]
+  fn fit() {
+    assert_eq!(
+      Viewport::new((300, 100), (10, 10), Scaling::Fit),
+      viewport(100.0, 0.0, 100.0, 100.0)
+    );
+    assert_eq!(
+      Viewport::new((100, 250), (20, 10), Scaling::Fit),
+      viewport(0.0, 100.0, 100.0, 50.0)
+    );
+  }
+
+  #[test]
+  fn integer() {
+    assert_eq!(
+      Viewport::new((105, 95), (10, 10), Scaling::Integer),
+      viewport(7.0, 2.0, 90.0, 90.0)
+    );
+    assert_eq!(
+      Viewport::new((5, 5), (10, 10), Scaling::Integer),
+      viewport(0.0, 0.0, 5.0, 5.0)
+    );
+  }
+
+  #[test]
+  fn window_to_pixel() {
+    let viewport = Viewport::new((300, 100), (10, 10), Scaling::Fit);
+    assert_eq!(
+      viewport.window_to_pixel(PhysicalPosition::new(150.0, 25.0), (10, 10)),
+      (5.0, 2.5)
+    );
+  }
+}